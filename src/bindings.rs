@@ -0,0 +1,66 @@
+// Values given to variables for evaluating an expression.
+
+use crate::error::EvalError;
+use crate::token::Variable;
+use std::collections::HashMap;
+
+/// The values of variables, e.g. `x = 2`, used when evaluating an expression with
+/// `Expr::eval_with` or `Tree::evaluate_with`. The same bindings can be updated and reused to
+/// evaluate an expression for many sets of values.
+/// Values are `f64` unless another `Numeric` type is chosen, e.g. `Bindings<Complex>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings<N = f64>(HashMap<Variable, N>);
+
+impl<N: Copy> Bindings<N> {
+    /// Bindings without any variables.
+    pub fn new() -> Self {
+        Bindings(HashMap::new())
+    }
+
+    /// Gives `variable` the `value`, returning its previous value if it had one.
+    pub fn insert(&mut self, variable: Variable, value: N) -> Option<N> {
+        self.0.insert(variable, value)
+    }
+
+    /// Same as `Bindings::insert`, for building bindings in one expression, e.g.
+    /// `Bindings::new().with(Variable::X, 1.0).with(Variable::Y, 2.0)`.
+    pub fn with(mut self, variable: Variable, value: N) -> Self {
+        self.0.insert(variable, value);
+        self
+    }
+
+    /// Removes the value of `variable`, returning it if it had one.
+    pub fn remove(&mut self, variable: Variable) -> Option<N> {
+        self.0.remove(&variable)
+    }
+
+    pub fn get(&self, variable: Variable) -> Option<N> {
+        self.0.get(&variable).copied()
+    }
+
+    /// Checks that each of `variables` has a value, returning the ones which do not, in the same
+    /// order, as `EvalError::UnboundVariables`.
+    pub fn check(&self, variables: &[Variable]) -> Result<(), EvalError> {
+        let unbound: Vec<Variable> = variables
+            .iter()
+            .filter(|variable| !self.0.contains_key(variable))
+            .copied()
+            .collect();
+        match unbound.is_empty() {
+            true => Ok(()),
+            false => Err(EvalError::UnboundVariables { variables: unbound }),
+        }
+    }
+}
+
+impl<N: Copy> Default for Bindings<N> {
+    fn default() -> Self {
+        Bindings::new()
+    }
+}
+
+impl<N: Copy> FromIterator<(Variable, N)> for Bindings<N> {
+    fn from_iter<T: IntoIterator<Item = (Variable, N)>>(iter: T) -> Self {
+        Bindings(iter.into_iter().collect())
+    }
+}
@@ -0,0 +1,199 @@
+// Human-readable reports of errors in an input string, e.g.
+//
+// error: unknown symbol
+//   |
+// 1 | 2 * sinq(x)
+//   |     ^^^^
+//   = hint: did you mean `sin`?
+
+use crate::error::ParseError;
+use crate::lexer::WORDS;
+use crate::token::Span;
+
+/// An error message attached to a span of the input string, with an optional hint on how to fix
+/// it. Rendered with `render` for plain text or `render_ansi` for a colored terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub hint: Option<String>,
+}
+
+// The escape codes used to color each part of a rendered diagnostic.
+struct Style {
+    error: &'static str,
+    caret: &'static str,
+    hint: &'static str,
+    reset: &'static str,
+}
+
+const PLAIN: Style = Style {
+    error: "",
+    caret: "",
+    hint: "",
+    reset: "",
+};
+
+const ANSI: Style = Style {
+    error: "\x1b[1;31m",
+    caret: "\x1b[1;31m",
+    hint: "\x1b[1;36m",
+    reset: "\x1b[0m",
+};
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic {
+            message: message.into(),
+            span,
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Renders the diagnostic as plain text, showing the line of `source` containing the span
+    /// with the span underlined by carets.
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, &PLAIN)
+    }
+
+    /// Same as `render`, but colored with ANSI escape codes for display in a terminal.
+    pub fn render_ansi(&self, source: &str) -> String {
+        self.render_with(source, &ANSI)
+    }
+
+    fn render_with(&self, source: &str, style: &Style) -> String {
+        // A span given by hand may end past `source` or inside a multi-byte character, so it is
+        // widened to the characters it touches.
+        let start = char_boundary(source, self.span.start, false);
+        let end = char_boundary(source, self.span.end.max(start), true);
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line_number = source[..start].matches('\n').count() + 1;
+        let line = &source[line_start..line_end];
+
+        // Columns are counted in characters rather than bytes so the carets line up with
+        // multi-byte characters. An empty span, e.g. at the end of the input, still gets a caret.
+        let column = source[line_start..start].chars().count();
+        let width = source[start..end.min(line_end)].chars().count().max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        let mut out = format!("{}error{}: {}\n", style.error, style.reset, self.message);
+        out += &format!("{} |\n", gutter);
+        out += &format!("{} | {}\n", line_number, line);
+        out += &format!(
+            "{} | {}{}{}{}\n",
+            gutter,
+            " ".repeat(column),
+            style.caret,
+            "^".repeat(width),
+            style.reset
+        );
+        if let Some(hint) = &self.hint {
+            out += &format!("{} = {}hint{}: {}\n", gutter, style.hint, style.reset, hint);
+        }
+        out
+    }
+}
+
+// The nearest char boundary of `source` at or before `index`, or at or after it when `up`, with
+// `index` clamped to the length of `source`.
+fn char_boundary(source: &str, index: usize, up: bool) -> usize {
+    let mut index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        match up {
+            true => index += 1,
+            false => index -= 1,
+        }
+    }
+    index
+}
+
+impl ParseError {
+    /// Turns the error into a `Diagnostic` for the input string `source` it was returned for,
+    /// with a hint where one can be guessed from the offending text.
+    pub fn diagnostic(&self, source: &str) -> Diagnostic {
+        let span = self.span();
+        let text = source.get(span.start..span.end).unwrap_or("");
+        let diagnostic = Diagnostic::new(self.message(), span);
+        let hint = match self {
+            ParseError::UnknownSymbol { .. } => {
+                closest_word(text).map(|word| format!("did you mean `{}`?", word))
+            }
+            ParseError::MalformedNumber { .. } if text.matches('.').count() > 1 => {
+                Some(String::from("a number can only contain one `.`"))
+            }
+            ParseError::MalformedNumber { .. } => None,
+            ParseError::UnbalancedParen { .. } if text == "(" => {
+                Some(String::from("missing `)` for the `(` opened here"))
+            }
+            ParseError::UnbalancedParen { .. } => {
+                Some(String::from("this `)` has no matching `(`"))
+            }
+            ParseError::DanglingOperator { .. } => {
+                Some(format!("`{}` needs an operand on each side", text))
+            }
+            ParseError::MissingOperator { .. } => {
+                Some(String::from("add an operator, e.g. `*`, before this"))
+            }
+            ParseError::MisplacedComma { .. } => Some(String::from(
+                "commas can only separate the arguments of a function, e.g. `log(2, x)`",
+            )),
+            ParseError::WrongArgumentCount { .. } => None,
+            ParseError::EmptyInput { .. } if text.starts_with('(') => {
+                Some(String::from("put an expression between the parentheses"))
+            }
+            ParseError::EmptyInput { .. } => None,
+            ParseError::MalformedRule { .. } if !source.contains("=>") => Some(String::from(
+                "write the rule as `pattern => replacement`, e.g. `?a + 0 => ?a`",
+            )),
+            ParseError::MalformedRule { .. } => Some(String::from(
+                "conditions compare two expressions, e.g. `if ?a != 0 and ?b > 1`",
+            )),
+            ParseError::UnboundMetavariable { .. } => Some(format!(
+                "`{}` has to appear in the pattern, before the `=>`",
+                text
+            )),
+            ParseError::WrongOrdering { .. } => None,
+        };
+        match hint {
+            Some(hint) => diagnostic.with_hint(hint),
+            None => diagnostic,
+        }
+    }
+}
+
+// The word known to the lexer which is closest to `text`, if any is close enough to be a likely
+// typo, e.g. "sine" or "son" for "sin".
+fn closest_word(text: &str) -> Option<&'static str> {
+    if text.chars().count() < 2 {
+        return None;
+    }
+    WORDS
+        .iter()
+        .map(|(word, _)| (*word, edit_distance(text, word)))
+        .filter(|(word, distance)| *distance <= 2 && *distance < word.len())
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(word, _)| word)
+}
+
+// Levenshtein distance between two strings, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,235 @@
+// Errors returned while turning an input string into tokens and then into a tree.
+
+use crate::num::Numeric;
+use crate::token::Operator;
+use crate::token::Span;
+use crate::token::Token;
+use crate::token::UnaryOperator;
+use crate::token::Variable;
+use std::error::Error;
+use std::fmt::Display;
+
+/// Everything that can go wrong while reading an expression. Each variant carries the byte span
+/// of the input string responsible for the error, so callers can point at the exact character.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParseError {
+    /// A character or word the lexer does not recognize, e.g. the `$` in `2 $ 3`.
+    UnknownSymbol { span: Span },
+    /// Something that starts like a number but cannot be read as one, e.g. `1.2.3`.
+    MalformedNumber { span: Span },
+    /// A `(` without a matching `)`, or the other way around.
+    UnbalancedParen { span: Span },
+    /// An operator missing one of its operands, e.g. the `+` in `1 +`.
+    DanglingOperator { span: Span },
+    /// Two operands with nothing joining them, e.g. the `2` in `1 2`.
+    MissingOperator { span: Span },
+    /// A comma anywhere but between the arguments of a function, e.g. in `(1, 2) + 3`.
+    MisplacedComma { span: Span },
+    /// A function given the wrong number of arguments, e.g. the `sin` in `sin(1, 2)`.
+    WrongArgumentCount { span: Span },
+    /// There is nothing to parse, either in the whole input or between a pair of parentheses.
+    EmptyInput { span: Span },
+    /// A rewrite rule which is not written as `pattern => replacement`, optionally followed by
+    /// `if` and conditions, e.g. a rule without `=>` or a condition without a comparison.
+    MalformedRule { span: Span },
+    /// A metavariable in the replacement or a condition of a rewrite rule which is not in its
+    /// pattern, e.g. the `?c` in `?a + ?b => ?c`.
+    UnboundMetavariable { span: Span },
+    /// A list of tokens in the wrong order for a conversion, e.g. `Lexer::in_to_pre` on a list
+    /// which is not in-order. The span covers the whole list.
+    WrongOrdering { span: Span },
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        let mut error = *self;
+        *error.span_mut()
+    }
+
+    /// The same error, for the same text found `offset` bytes further into a longer string.
+    pub(crate) fn offset(mut self, offset: usize) -> ParseError {
+        let span = self.span_mut();
+        *span = Span::new(span.start + offset, span.end + offset);
+        self
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            ParseError::UnknownSymbol { span }
+            | ParseError::MalformedNumber { span }
+            | ParseError::UnbalancedParen { span }
+            | ParseError::DanglingOperator { span }
+            | ParseError::MissingOperator { span }
+            | ParseError::MisplacedComma { span }
+            | ParseError::WrongArgumentCount { span }
+            | ParseError::EmptyInput { span }
+            | ParseError::MalformedRule { span }
+            | ParseError::UnboundMetavariable { span }
+            | ParseError::WrongOrdering { span } => span,
+        }
+    }
+
+    /// A short description of the error, without its position.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ParseError::UnknownSymbol { .. } => "unknown symbol",
+            ParseError::MalformedNumber { .. } => "malformed number",
+            ParseError::UnbalancedParen { .. } => "unbalanced parenthesis",
+            ParseError::DanglingOperator { .. } => "operator is missing an operand",
+            ParseError::MissingOperator { .. } => "missing operator between operands",
+            ParseError::MisplacedComma { .. } => "comma outside of function arguments",
+            ParseError::WrongArgumentCount { .. } => "wrong number of arguments",
+            ParseError::EmptyInput { .. } => "empty input",
+            ParseError::MalformedRule { .. } => "malformed rule",
+            ParseError::UnboundMetavariable { .. } => "metavariable is not in the pattern",
+            ParseError::WrongOrdering { .. } => "tokens are not in-order",
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span())
+    }
+}
+
+impl Error for ParseError {}
+
+/// Everything that can go wrong while building a tree from a list of tokens. Each variant
+/// carries the index of the token in the list which broke the grammar.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TreeError {
+    /// A `(` without a matching `)`, or the other way around.
+    UnbalancedParen { index: usize },
+    /// An operator missing one of its operands, e.g. the `+` in `1 +`, or the `*` in `1 + * 2`.
+    DanglingOperator { index: usize },
+    /// Something in the place of an operator, e.g. the `2` in `1 2` or the `sin` in `1 sin x`.
+    MissingOperator { index: usize },
+    /// A comma anywhere but between the arguments of a function, e.g. in `(1, 2) + 3`.
+    MisplacedComma { index: usize },
+    /// A function given the wrong number of arguments, e.g. the `sin` in `sin(1, 2)`.
+    WrongArgumentCount { index: usize },
+    /// There are no tokens, in which case `index` is 0, or nothing between the `(` at `index`
+    /// and its `)`.
+    EmptyInput { index: usize },
+}
+
+impl TreeError {
+    pub fn index(&self) -> usize {
+        match self {
+            TreeError::UnbalancedParen { index }
+            | TreeError::DanglingOperator { index }
+            | TreeError::MissingOperator { index }
+            | TreeError::MisplacedComma { index }
+            | TreeError::WrongArgumentCount { index }
+            | TreeError::EmptyInput { index } => *index,
+        }
+    }
+}
+
+impl Display for TreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            TreeError::UnbalancedParen { .. } => "unbalanced parenthesis",
+            TreeError::DanglingOperator { .. } => "operator is missing an operand",
+            TreeError::MissingOperator { .. } => "missing operator between operands",
+            TreeError::MisplacedComma { .. } => "comma outside of function arguments",
+            TreeError::WrongArgumentCount { .. } => "wrong number of arguments",
+            TreeError::EmptyInput { .. } => "empty input",
+        };
+        write!(f, "{} at token {}", message, self.index())
+    }
+}
+
+impl Error for TreeError {}
+
+/// Everything that can go wrong while evaluating an expression to a number. Each variant which
+/// is caused by a single token carries the index of that token in the list being evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// A division by zero, e.g. `1 / 0`, or zero raised to a negative power, e.g. `0^-1`.
+    DivisionByZero { index: usize },
+    /// An operator or function applied outside of its domain, e.g. `sqrt(-1)` or `ln(0)`, or an
+    /// imaginary number where only real numbers are allowed.
+    Domain { index: usize },
+    /// A result too large to be represented, e.g. `2^(10^9)` or `exp(1000)`.
+    Overflow { index: usize },
+    /// An operator with fewer operands on the stack than it takes, e.g. the `+` in `1 +` written
+    /// in post-order. An empty list of tokens underflows at index 0.
+    StackUnderflow { index: usize },
+    /// More than one value left on the stack after the last token, e.g. `1 2` in post-order.
+    ExtraOperands { index: usize },
+    /// A function given the wrong number of arguments, e.g. `hypot(1)`.
+    WrongArgumentCount { index: usize },
+    /// Variables without a value, listing each of them once.
+    UnboundVariables { variables: Vec<Variable> },
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (message, index) = match self {
+            EvalError::DivisionByZero { index } => ("division by zero", index),
+            EvalError::Domain { index } => ("outside of the domain", index),
+            EvalError::Overflow { index } => ("result is too large", index),
+            EvalError::StackUnderflow { index } => ("operator is missing an operand", index),
+            EvalError::ExtraOperands { index } => ("operands left without an operator", index),
+            EvalError::WrongArgumentCount { index } => ("wrong number of arguments", index),
+            EvalError::UnboundVariables { variables } => {
+                let names: Vec<&str> = variables.iter().map(|variable| variable.name()).collect();
+                return write!(f, "no value for {}", names.join(", "));
+            }
+        };
+        write!(f, "{} at token {}", message, index)
+    }
+}
+
+impl Error for EvalError {}
+
+impl EvalError {
+    /// Checks the `result` of applying the operator or function `token` to `operands`, returning
+    /// the error for an operation which has no value, e.g. `1 / 0` or `sqrt(-1)`. The token is at
+    /// `index` in the list being evaluated. A result which is infinite although none of
+    /// `operands` is, e.g. `2^(10^9)`, is an overflow.
+    pub(crate) fn check<N: Numeric>(
+        token: Token,
+        operands: &[N],
+        result: N,
+        index: usize,
+    ) -> Result<(), EvalError> {
+        let zero = N::from_f64(0.0);
+        let one = N::from_f64(1.0);
+        match (token, operands) {
+            (Token::Op(Operator::Divide), [_, right]) if *right == zero => {
+                Err(EvalError::DivisionByZero { index })
+            }
+            (Token::Op(Operator::Exponent), [left, right])
+                if *left == zero && right.to_f64() < 0.0 =>
+            {
+                Err(EvalError::DivisionByZero { index })
+            }
+            // The logarithm of 0 is an infinity, rather than NaN.
+            (
+                Token::Op(Operator::Logarithm)
+                | Token::UnOp(
+                    UnaryOperator::NaturalLogarithm
+                    | UnaryOperator::CommonLogarithm
+                    | UnaryOperator::BinaryLogarithm,
+                ),
+                [.., x],
+            ) if *x == zero => Err(EvalError::Domain { index }),
+            // A logarithm has a positive base other than 1, as there is no power of 1 but 1.
+            (Token::Op(Operator::Logarithm), [base, _]) if *base == one || base.to_f64() <= 0.0 => {
+                Err(EvalError::Domain { index })
+            }
+            // A result which is not a number although none of its operands were, e.g. `sqrt(-1)`,
+            // is outside the domain of the operator.
+            _ if result.is_nan() && !operands.iter().any(|operand| operand.is_nan()) => {
+                Err(EvalError::Domain { index })
+            }
+            _ if result.is_infinite() && !operands.iter().any(|operand| operand.is_infinite()) => {
+                Err(EvalError::Overflow { index })
+            }
+            _ => Ok(()),
+        }
+    }
+}
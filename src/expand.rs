@@ -0,0 +1,421 @@
+// Expanding products and powers of sums into a sum of terms, with the like terms collected.
+
+use crate::lexer::Ordering;
+use crate::num::Num;
+use crate::num::Rational;
+use crate::simplify::fold;
+use crate::token::Operator;
+use crate::token::Token;
+use crate::token::UnaryOperator;
+use crate::tree::Assumptions;
+use crate::tree::Tree;
+use binary_tree_ds::*;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type TreeNodeRef<T> = Rc<RefCell<TreeNode<T>>>;
+
+// The most products of terms worked out in a single multiplication while raising a sum to a
+// power, above which the power is kept as it is, e.g. `(x + 1)^1000000`.
+const MAX_PRODUCTS: usize = 10_000;
+
+// An expanded expression, the sum of its terms. No two terms have the same factors, and only a
+// term with divisors has a coefficient of 0, e.g. `0 / x`, as it has no value where they are 0,
+// so 0 is the empty sum.
+pub(crate) type Sum = Vec<Term>;
+
+// A number times a product of factors.
+#[derive(Clone)]
+pub(crate) struct Term {
+    pub(crate) coefficient: Num,
+    // Sorted by key and then divisors last, with no two factors having the same key unless one of
+    // them is a divisor, and none having a power of 0. A term with a coefficient of 0 only has
+    // divisors.
+    pub(crate) factors: Vec<Factor>,
+}
+
+// An expression which cannot be expanded any further, e.g. `x`, `sin(x)` or `(x + 1)` when dividing
+// by it, raised to an integer power.
+#[derive(Clone)]
+pub(crate) struct Factor {
+    // The expression printed in order, which is the same for equal expressions.
+    pub(crate) key: String,
+    pub(crate) node: TreeNodeRef<Token>,
+    pub(crate) power: i128,
+    // Whether the term is divided by the factor to its power, when the factor is not known to be
+    // nonzero, e.g. `x` in `x / x`. It is kept apart from the factor multiplying the term, so the
+    // two never cancel. A factor known to be nonzero is divided by with a negative power instead.
+    pub(crate) divisor: bool,
+}
+
+impl Tree {
+    /// Expands the tree into a sum of terms, by distributing multiplication over addition and
+    /// subtraction, e.g. `a * (b + c) => ab + ac`, and working out integer powers of sums, e.g.
+    /// `(a + b)^2 => a^2 + 2ab + b^2`, then collects the like terms, e.g. `x(x + 1) - x => x^2`.
+    /// Dividing by a single term divides each term by it, e.g. `(2x + 4) / 2 => x + 2`, while a
+    /// sum being divided by, e.g. `1 / (x + 1)`, or anything else which cannot be expanded, e.g.
+    /// `sin(x)`, is kept as a factor with its own contents expanded. So is a power of a sum with
+    /// too many terms to work out, e.g. `(x + 1)^1000000`.
+    /// A factor is only cancelled when it is divided by if it is known not to be 0, e.g. `pi`, so
+    /// `x / x` and `x^2 / x` are kept as they are, see `Tree::expand_with`. Neither is `0 / x`
+    /// taken to be 0, as it has no value where `x` is 0.
+    /// The terms are ordered by degree, highest first, then by their factors, so that equal
+    /// expressions expand to equal trees.
+    pub fn expand(&self) -> Tree {
+        self.expand_with(&Assumptions::default())
+    }
+
+    /// Same as `Tree::expand`, with factors which `assumptions` show are not 0 cancelled when
+    /// divided by, e.g. `(x^2 + x) / x => x + 1` if `x` is assumed to be nonzero.
+    pub fn expand_with(&self, assumptions: &Assumptions) -> Tree {
+        Tree::new(sum_node(expand_node(&fold(self.root()), assumptions)))
+    }
+}
+
+/// Recursive function used in method `expand_with`.
+pub(crate) fn expand_node(node_ref: &TreeNodeRef<Token>, assumptions: &Assumptions) -> Sum {
+    let node = node_ref.borrow();
+    let operand =
+        |child: &Option<TreeNodeRef<Token>>| expand_node(child.as_ref().unwrap(), assumptions);
+    let factor = |node: TreeNodeRef<Token>, power: i128| factor(node, power, assumptions);
+    match node.value {
+        Token::Number(num) => constant(num),
+        Token::Op(Operator::Add) => add(operand(&node.left), operand(&node.right)),
+        Token::Op(Operator::Subtract) => add(operand(&node.left), negate(operand(&node.right))),
+        Token::Op(Operator::Multiply) => multiply(&operand(&node.left), &operand(&node.right)),
+        Token::Op(Operator::Divide) => {
+            let divisor = operand(&node.right);
+            let inverse =
+                power(&divisor, -1, assumptions).unwrap_or_else(|| factor(sum_node(divisor), -1));
+            multiply(&operand(&node.left), &inverse)
+        }
+        Token::Op(Operator::Exponent) => {
+            let base = operand(&node.left);
+            let exponent = operand(&node.right);
+            match integer(&exponent) {
+                Some(exponent) => power(&base, exponent, assumptions)
+                    .unwrap_or_else(|| factor(sum_node(base), exponent)),
+                None => factor(
+                    TreeNode::new_rc(node.value, Some(sum_node(base)), Some(sum_node(exponent))),
+                    1,
+                ),
+            }
+        }
+        Token::UnOp(UnaryOperator::Negation) => negate(operand(&node.right)),
+        _ => factor(expand_inside(node_ref, assumptions), 1),
+    }
+}
+
+/// Copies the expression under `node_ref` with its operands, or the arguments of a function,
+/// expanded, e.g. `sin(2(x + 1)) => sin(2x + 2)`.
+fn expand_inside(node_ref: &TreeNodeRef<Token>, assumptions: &Assumptions) -> TreeNodeRef<Token> {
+    let node = node_ref.borrow();
+    let child = |child: &TreeNodeRef<Token>| match child.borrow().value {
+        Token::Comma => expand_inside(child, assumptions),
+        _ => sum_node(expand_node(child, assumptions)),
+    };
+    TreeNode::new_rc(
+        node.value,
+        node.left.as_ref().map(child),
+        node.right.as_ref().map(child),
+    )
+}
+
+pub(crate) fn constant(num: Num) -> Sum {
+    match num.is_zero() {
+        true => vec![],
+        false => vec![Term {
+            coefficient: num,
+            factors: vec![],
+        }],
+    }
+}
+
+/// The term with the single factor `node` raised to `power`, which is a divisor when the power is
+/// negative and `assumptions` do not show `node` is nonzero.
+fn factor(node: TreeNodeRef<Token>, power: i128, assumptions: &Assumptions) -> Sum {
+    let key = Tree::new(node.clone())
+        .create_vec(Ordering::In)
+        .iter()
+        .map(|token| token.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    let divisor = power < 0 && !assumptions.is_nonzero(&node);
+    let power = if divisor { -power } else { power };
+    vec![Term {
+        coefficient: Num::from(1),
+        factors: vec![Factor {
+            key,
+            node,
+            power,
+            divisor,
+        }],
+    }]
+}
+
+/// The value of a sum which is an integer, e.g. the exponent of `x^2`.
+fn integer(sum: &Sum) -> Option<i128> {
+    match sum.as_slice() {
+        [] => Some(0),
+        [Term {
+            coefficient: Num::Rational(rational),
+            factors,
+        }] if factors.is_empty() => rational.to_i128(),
+        _ => None,
+    }
+}
+
+pub(crate) fn add(mut left: Sum, right: Sum) -> Sum {
+    let mut indices: HashMap<_, _> = left
+        .iter()
+        .enumerate()
+        .map(|(index, term)| (like(&term.factors), index))
+        .collect();
+    for term in right {
+        match indices.get(&like(&term.factors)) {
+            Some(&index) => left[index].coefficient = left[index].coefficient + term.coefficient,
+            None => {
+                indices.insert(like(&term.factors), left.len());
+                left.push(term);
+            }
+        }
+    }
+    // A term which adds up to 0 only keeps its divisors, unless another term already has them.
+    let (zero, mut sum): (Sum, Sum) = left
+        .into_iter()
+        .partition(|term| term.coefficient.is_zero());
+    for term in zero {
+        let divisors: Vec<Factor> = term
+            .factors
+            .into_iter()
+            .filter(|factor| factor.divisor)
+            .collect();
+        let kept = sum
+            .iter()
+            .any(|other| like(&other.factors) == like(&divisors));
+        if !divisors.is_empty() && !kept {
+            sum.push(Term {
+                coefficient: term.coefficient,
+                factors: divisors,
+            });
+        }
+    }
+    sum
+}
+
+/// What like terms have in common, the same factors to the same powers.
+fn like(factors: &[Factor]) -> Vec<(String, i128, bool)> {
+    factors
+        .iter()
+        .map(|factor| (factor.key.clone(), factor.power, factor.divisor))
+        .collect()
+}
+
+/// The order of the factors of a term, by key and then divisors last.
+fn order(factor: &Factor) -> (&str, bool) {
+    (&factor.key, factor.divisor)
+}
+
+fn negate(sum: Sum) -> Sum {
+    sum.into_iter()
+        .map(|term| Term {
+            coefficient: -term.coefficient,
+            ..term
+        })
+        .collect()
+}
+
+fn multiply(left: &Sum, right: &Sum) -> Sum {
+    // 0 times a term with divisors keeps them, as the product has no value where they are 0.
+    if left.is_empty() || right.is_empty() {
+        let zero = left
+            .iter()
+            .chain(right)
+            .map(|term| Term {
+                coefficient: Num::from(0),
+                factors: term.factors.clone(),
+            })
+            .collect();
+        return add(vec![], zero);
+    }
+    let mut product = Vec::with_capacity(left.len() * right.len());
+    for a in left {
+        for b in right {
+            let mut term = a.clone();
+            term.coefficient = a.coefficient * b.coefficient;
+            for factor in &b.factors {
+                match term
+                    .factors
+                    .binary_search_by(|other| order(other).cmp(&order(factor)))
+                {
+                    Ok(index) => {
+                        term.factors[index].power += factor.power;
+                        if term.factors[index].power == 0 {
+                            term.factors.remove(index);
+                        }
+                    }
+                    Err(index) => term.factors.insert(index, factor.clone()),
+                }
+            }
+            product.push(term);
+        }
+    }
+    add(vec![], product)
+}
+
+/// The sum raised to the integer `exponent`, or `None` when it is kept as a power: for a negative
+/// exponent of a sum which is not a single term, e.g. `(x + 1)^-1`, or of a term with divisors,
+/// e.g. `(1 / x)^-1`, a power of a single term too large to work out, e.g. `(2x)^(10^9)`, or a
+/// power of a sum with too many terms, e.g. `(x + 1)^1000000`. A factor of the term which
+/// `assumptions` do not show is nonzero becomes a divisor for a negative exponent.
+fn power(sum: &Sum, exponent: i128, assumptions: &Assumptions) -> Option<Sum> {
+    match (sum.as_slice(), exponent) {
+        (_, 0) => Some(constant(Num::from(1))),
+        ([], _) => (exponent > 0).then(Vec::new),
+        ([term], ..0) if term.factors.iter().any(|factor| factor.divisor) => None,
+        ([term], _) => {
+            let exponent_num = Num::Rational(Rational::new(exponent, 1).unwrap());
+            let coefficient = term.coefficient.pow(exponent_num);
+            let exact = matches!(coefficient, Num::Rational(_))
+                || matches!(term.coefficient, Num::Float(_));
+            let mut factors = term
+                .factors
+                .iter()
+                .map(|factor| {
+                    let power = factor.power.checked_mul(exponent)?;
+                    let divisor =
+                        factor.divisor || power < 0 && !assumptions.is_nonzero(&factor.node);
+                    Some(Factor {
+                        power: if divisor { power.abs() } else { power },
+                        divisor,
+                        ..factor.clone()
+                    })
+                })
+                .collect::<Option<Vec<Factor>>>()?;
+            factors.sort_by(|a, b| order(a).cmp(&order(b)));
+            exact.then_some(vec![Term {
+                coefficient,
+                factors,
+            }])
+        }
+        (_, ..0) => None,
+        _ => {
+            // Squaring the sum for each bit of the exponent.
+            let mut product = constant(Num::from(1));
+            let mut square = sum.clone();
+            let mut exponent = exponent;
+            loop {
+                if exponent & 1 == 1 {
+                    product = limited(&product, &square)?;
+                }
+                exponent >>= 1;
+                if exponent == 0 {
+                    return Some(product);
+                }
+                square = limited(&square, &square)?;
+            }
+        }
+    }
+}
+
+/// The product of two sums, or `None` if it would take more than `MAX_PRODUCTS` products of terms.
+fn limited(left: &Sum, right: &Sum) -> Option<Sum> {
+    (left.len() * right.len() <= MAX_PRODUCTS).then(|| multiply(left, right))
+}
+
+/// Orders the terms by degree, highest first, then by their factors.
+pub(crate) fn sort(sum: &mut Sum) {
+    let degree = |term: &Term| {
+        term.factors
+            .iter()
+            .map(|factor| match factor.divisor {
+                true => -factor.power,
+                false => factor.power,
+            })
+            .sum::<i128>()
+    };
+    sum.sort_by(|a, b| {
+        let factors = |term: &Term| {
+            term.factors
+                .iter()
+                .map(|factor| (factor.key.clone(), factor.divisor, Reverse(factor.power)))
+                .collect::<Vec<_>>()
+        };
+        degree(b)
+            .cmp(&degree(a))
+            .then_with(|| factors(a).cmp(&factors(b)))
+    });
+}
+
+/// Builds the tree of an expanded expression, e.g. `x^2 - 2 * x + 1`.
+pub(crate) fn sum_node(mut sum: Sum) -> TreeNodeRef<Token> {
+    sort(&mut sum);
+    let mut terms = sum.into_iter();
+    let Some(first) = terms.next() else {
+        return number_node(Num::from(0));
+    };
+    terms.fold(term_node(&first), |sum, term| {
+        if term.coefficient.is_negative() {
+            let negated = Term {
+                coefficient: -term.coefficient,
+                ..term
+            };
+            let right = term_node(&negated);
+            TreeNode::new_rc(Token::Op(Operator::Subtract), Some(sum), Some(right))
+        } else {
+            TreeNode::new_rc(Token::Op(Operator::Add), Some(sum), Some(term_node(&term)))
+        }
+    })
+}
+
+/// Builds the tree of a single term, e.g. `3 * x * y^2 / (x + 1)`, or `-x` for a coefficient of -1.
+fn term_node(term: &Term) -> TreeNodeRef<Token> {
+    let (numerator, denominator): (Vec<&Factor>, Vec<&Factor>) = term
+        .factors
+        .iter()
+        .partition(|factor| !factor.divisor && factor.power > 0);
+    let raised = |factor: &Factor| power_node(&factor.node, factor.power.abs());
+    let mut numerator: Vec<TreeNodeRef<Token>> = numerator.into_iter().map(raised).collect();
+    let coefficient = term.coefficient;
+    let negated = coefficient == Num::from(-1) && !numerator.is_empty();
+    if numerator.is_empty() || coefficient != Num::from(1) && !negated {
+        numerator.insert(0, number_node(coefficient));
+    }
+    let mut numerator = product(numerator).unwrap();
+    if negated {
+        numerator = TreeNode::new_rc(Token::UnOp(UnaryOperator::Negation), None, Some(numerator));
+    }
+    match product(denominator.into_iter().map(raised).collect()) {
+        Some(denominator) => TreeNode::new_rc(
+            Token::Op(Operator::Divide),
+            Some(numerator),
+            Some(denominator),
+        ),
+        None => numerator,
+    }
+}
+
+pub(crate) fn number_node(num: Num) -> TreeNodeRef<Token> {
+    TreeNode::new_rc(Token::Number(num), None, None)
+}
+
+/// The tree of `node` raised to `power`, or `node` itself for a power of 1.
+pub(crate) fn power_node(node: &TreeNodeRef<Token>, power: i128) -> TreeNodeRef<Token> {
+    match power {
+        1 => node.clone(),
+        power => TreeNode::new_rc(
+            Token::Op(Operator::Exponent),
+            Some(node.clone()),
+            Some(number_node(Num::Rational(Rational::new(power, 1).unwrap()))),
+        ),
+    }
+}
+
+/// The product of `factors`, multiplied from left to right, or `None` if there are none.
+pub(crate) fn product(factors: Vec<TreeNodeRef<Token>>) -> Option<TreeNodeRef<Token>> {
+    factors.into_iter().reduce(|product, factor| {
+        TreeNode::new_rc(Token::Op(Operator::Multiply), Some(product), Some(factor))
+    })
+}
@@ -3,32 +3,116 @@
 // another example:
 // https://play.rust-lang.org/?version=stable&mode=debug&edition=2021&gist=1e93173b55bdad5e2908e32823611202
 
+use crate::bindings::Bindings;
+use crate::error::EvalError;
+use crate::lexer::Ordering;
 use crate::token::Token;
-use binary_tree_ds::*;
+use crate::token::Variable;
+use crate::tree::Tree;
 
+/// A flat expression of post-order (reverse polish) tokens, e.g. `1 2 3 * +` for `1 + 2 * 3`,
+/// evaluated with a stack instead of walking a tree.
 pub struct Expr(Vec<Token>);
 
 impl Expr {
-    /// Evaluates a statement from tokens. This version relies on post-fix (reverse polish) notation
     // We may wish to change this to prefix at some point.
-    pub fn eval(&self) -> () /* Stmt::Expr */ {
-        let mut stack: Vec<f32> = vec![];
-        // TODO: make this work
-        // This doesn't actually work - it needs to have a while loop.
+    /// Evaluates a statement from tokens. This version relies on post-fix (reverse polish) notation.
+    /// Each number, constant or variable is pushed onto a stack, and each operator pops its
+    /// operands and pushes its result. The arguments of a function are gathered by the commas
+    /// between them, e.g. `1 2 , 3 , max` is `max(1, 2, 3)`.
+    /// Returns an `EvalError` for a division by zero, an operator applied outside its domain, e.g.
+    /// `ln(0)`, a result too large to represent, e.g. `2^(10^9)`, or a list of tokens which is
+    /// not a well-formed post-order expression.
+    /// An expression with variables cannot be evaluated this way, see `Expr::eval_with`.
+    pub fn eval(&self) -> Result<f64, EvalError> {
+        self.eval_with(&Bindings::new())
+    }
+
+    /// Same as `Expr::eval`, with each variable taking its value from `bindings`. Variables
+    /// without a value are all listed in `EvalError::UnboundVariables`.
+    pub fn eval_with(&self, bindings: &Bindings) -> Result<f64, EvalError> {
+        bindings.check(&self.variables())?;
+        let mut stack: Vec<f64> = vec![];
+        // The number of values making up each entry of the stack. Operands are a single value,
+        // while the arguments of a function joined by commas are a single entry of many values.
+        let mut widths: Vec<usize> = vec![];
+        for (index, token) in self.0.iter().enumerate() {
+            let mut pop = |widths: &mut Vec<usize>| match widths.pop() {
+                Some(1) => Ok(stack.pop().unwrap()),
+                // Argument lists can only be used by a function.
+                Some(_) => Err(EvalError::WrongArgumentCount { index }),
+                None => Err(EvalError::StackUnderflow { index }),
+            };
+            let value = match *token {
+                Token::Number(num) => num.to_f64(),
+                Token::Const(constant) => constant.value(),
+                Token::Imaginary(_) => return Err(EvalError::Domain { index }),
+                Token::Var(variable) => bindings.get(variable).unwrap(),
+                // A metavariable stands for an expression, which never has a value.
+                Token::Meta(variable) => {
+                    return Err(EvalError::UnboundVariables {
+                        variables: vec![variable],
+                    })
+                }
+                Token::Op(op) => {
+                    let right = pop(&mut widths)?;
+                    let left = pop(&mut widths)?;
+                    let result = op.apply(left, right);
+                    EvalError::check(*token, &[left, right], result, index)?;
+                    result
+                }
+                Token::UnOp(un_op) => {
+                    let x = pop(&mut widths)?;
+                    let result = un_op.apply(x);
+                    EvalError::check(*token, &[x], result, index)?;
+                    result
+                }
+                Token::Func(function) => {
+                    let count = widths.pop().ok_or(EvalError::StackUnderflow { index })?;
+                    if !function.accepts(count) {
+                        return Err(EvalError::WrongArgumentCount { index });
+                    }
+                    let arguments = stack.split_off(stack.len() - count);
+                    let result = function.apply(&arguments);
+                    EvalError::check(*token, &arguments, result, index)?;
+                    result
+                }
+                Token::Comma => {
+                    let right = widths.pop().ok_or(EvalError::StackUnderflow { index })?;
+                    let left = widths.pop().ok_or(EvalError::StackUnderflow { index })?;
+                    widths.push(left + right);
+                    continue;
+                }
+                // Post-order has no need for parentheses.
+                Token::LParen | Token::RParen => return Err(EvalError::StackUnderflow { index }),
+            };
+            stack.push(value);
+            widths.push(1);
+        }
+        match widths[..] {
+            [1] => Ok(stack[0]),
+            [] => Err(EvalError::StackUnderflow { index: 0 }),
+            [_] => Err(EvalError::WrongArgumentCount {
+                index: self.0.len() - 1,
+            }),
+            _ => Err(EvalError::ExtraOperands {
+                index: self.0.len() - 1,
+            }),
+        }
+    }
+
+    /// The variables in the expression, each listed once in the order they first appear.
+    pub fn variables(&self) -> Vec<Variable> {
+        let mut variables = vec![];
         for token in self.0.iter() {
-            match token {
-                // TODO: Cover all cases of operator
-                Token::Number(n) => stack.push(*n),
-                Token::Op(crate::token::Operator::Multiply) => {
-                    let new = stack.pop().unwrap() * stack.pop().unwrap();
-                    stack.push(new)
+            if let Token::Var(variable) = token {
+                if !variables.contains(variable) {
+                    variables.push(*variable);
                 }
-                // more here
-                _ => todo!(), // TODO: Add error handling
             }
         }
+        variables
     }
-    // TODO: Add a way to assign values to variables and evaluate with those values.
 }
 
 impl From<Vec<Token>> for Expr {
@@ -36,3 +120,9 @@ impl From<Vec<Token>> for Expr {
         Self(value)
     }
 }
+
+impl From<&Tree> for Expr {
+    fn from(tree: &Tree) -> Self {
+        Self(tree.create_vec(Ordering::Post))
+    }
+}
@@ -0,0 +1,327 @@
+// Factoring an expression, the inverse of expanding it.
+
+use crate::expand::add;
+use crate::expand::constant;
+use crate::expand::expand_node;
+use crate::expand::number_node;
+use crate::expand::power_node;
+use crate::expand::product;
+use crate::expand::sort;
+use crate::expand::sum_node;
+use crate::expand::Factor;
+use crate::expand::Sum;
+use crate::expand::Term;
+use crate::num::Num;
+use crate::num::Rational;
+use crate::simplify::fold;
+use crate::token::Token;
+use crate::token::UnaryOperator;
+use crate::tree::Assumptions;
+use crate::tree::Tree;
+use binary_tree_ds::*;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::One;
+use num_traits::Zero;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type TreeNodeRef<T> = Rc<RefCell<TreeNode<T>>>;
+
+// Polynomials of a higher degree are not searched for roots, as their list of coefficients would
+// be too long. Such a degree only comes from a power of a single factor, e.g. `x^100000000`, as
+// expanding keeps a power of a sum as it is when it would have too many terms.
+const MAX_DEGREE: usize = 1000;
+// The largest coefficient whose divisors are tried as roots, as finding them takes up to its
+// square root steps.
+const MAX_DIVIDEND: i128 = 1_000_000_000_000;
+// The most steps spent looking for each root, counting one for each coefficient of the polynomial
+// with each candidate root, above which the polynomial is kept as it is. Without it e.g.
+// `963761198400 x^2 + x + 963761198400`, whose coefficients have thousands of divisors, would try
+// millions of candidates.
+const MAX_STEPS: usize = 100_000;
+
+impl Tree {
+    /// Factors the tree, the inverse of `Tree::expand`. The greatest common factor of the terms is
+    /// taken out, both of their numbers, e.g. `6x + 9 => 3(2x + 3)`, and of their factors, e.g.
+    /// `ab + ac => a(b + c)`. What is left, when it is a polynomial in a single variable, is then
+    /// split at its rational roots, e.g. `x^2 - 5x + 6 => (x - 2)(x - 3)`, and anything which
+    /// cannot be split, e.g. `x^2 + 1`, is kept as a sum.
+    /// The tree is expanded first, so a sum is factored the same however it is written, and the
+    /// result only has the parentheses it needs when printed with `create_vec(Ordering::In)`.
+    /// What a term is divided by is never taken out, as it only cancels when known to be nonzero,
+    /// see `Tree::expand`.
+    pub fn factor(&self) -> Tree {
+        let mut sum = expand_node(&fold(self.root()), &Assumptions::default());
+        if sum.len() < 2 {
+            return Tree::new(sum_node(sum));
+        }
+        sort(&mut sum);
+        let mut content = content(&sum);
+        let common = common_factors(&sum);
+        let rest: Sum = sum
+            .into_iter()
+            .map(|term| divide(term, content, &common))
+            .collect();
+        let mut factors: Vec<TreeNodeRef<Token>> = common
+            .iter()
+            .map(|factor| power_node(&factor.node, factor.power))
+            .collect();
+        match univariate(&rest) {
+            Some((variable, mut coefficients)) => {
+                let roots = rational_roots(&mut coefficients);
+                let mut index = 0;
+                while index < roots.len() {
+                    let multiplicity = roots[index..]
+                        .iter()
+                        .take_while(|root| **root == roots[index])
+                        .count();
+                    factors.push(power_node(
+                        &sum_node(linear(&variable, roots[index])),
+                        multiplicity as i128,
+                    ));
+                    index += multiplicity;
+                }
+                match coefficients.as_slice() {
+                    [remainder] => content = content * *remainder,
+                    _ => factors.push(sum_node(polynomial(&variable, &coefficients))),
+                }
+            }
+            None => factors.push(sum_node(rest)),
+        }
+        if content == Num::from(-1) {
+            factors[0] = TreeNode::new_rc(
+                Token::UnOp(UnaryOperator::Negation),
+                None,
+                Some(factors[0].clone()),
+            );
+        } else if content != Num::from(1) {
+            factors.insert(0, number_node(content));
+        }
+        Tree::new(product(factors).unwrap())
+    }
+}
+
+/// The greatest common divisor of the coefficients of `sum`, with the sign of its first term, e.g.
+/// 3 for `6x + 9`, 1/4 for `x/2 + 1/4` or -1 for `-x + 1`. Only the sign is taken out when any
+/// coefficient is a float.
+fn content(sum: &Sum) -> Num {
+    let sign = match sum[0].coefficient.is_negative() {
+        true => -1,
+        false => 1,
+    };
+    let rationals: Option<Vec<Rational>> = sum
+        .iter()
+        .map(|term| match term.coefficient {
+            Num::Rational(rational) => Some(rational),
+            Num::Float(_) => None,
+        })
+        .collect();
+    let divisor = rationals.map(|rationals| {
+        let numer = rationals.iter().fold(BigInt::zero(), |divisor, rational| {
+            divisor.gcd(&rational.numer())
+        });
+        let denom = rationals.iter().fold(BigInt::one(), |multiple, rational| {
+            multiple.lcm(&rational.denom())
+        });
+        Rational::from_big(BigRational::new(numer * sign, denom))
+    });
+    match divisor {
+        Some(divisor) => Num::Rational(divisor),
+        None => Num::from(sign),
+    }
+}
+
+/// The factors every term of `sum` has, to the lowest power they have them to, e.g. `x^2` for
+/// `x^3 y + x^2`. Divisors are left in the terms.
+fn common_factors(sum: &Sum) -> Vec<Factor> {
+    sum[0]
+        .factors
+        .iter()
+        .filter(|factor| !factor.divisor)
+        .filter_map(|factor| {
+            let power = sum.iter().try_fold(factor.power, |power, term| {
+                term.factors
+                    .iter()
+                    .find(|other| other.key == factor.key && !other.divisor)
+                    .map(|other| power.min(other.power))
+            })?;
+            (power > 0).then(|| Factor {
+                power,
+                ..factor.clone()
+            })
+        })
+        .collect()
+}
+
+/// Divides `term` by `content` and by each of the `common` factors, which it has.
+fn divide(mut term: Term, content: Num, common: &[Factor]) -> Term {
+    term.coefficient = term.coefficient / content;
+    for factor in common {
+        let index = term
+            .factors
+            .iter()
+            .position(|other| other.key == factor.key && !other.divisor)
+            .unwrap();
+        term.factors[index].power -= factor.power;
+        if term.factors[index].power == 0 {
+            term.factors.remove(index);
+        }
+    }
+    term
+}
+
+/// The variable of `sum` and its coefficients, from the constant term up, when `sum` is a
+/// polynomial in a single variable with rational coefficients, e.g. `x` and [6, -5, 1] for
+/// `x^2 - 5x + 6`.
+fn univariate(sum: &Sum) -> Option<(Factor, Vec<Num>)> {
+    let variable = Factor {
+        power: 1,
+        ..sum.iter().find_map(|term| term.factors.first())?.clone()
+    };
+    let mut coefficients = vec![];
+    for term in sum {
+        let power = match term.factors.as_slice() {
+            [] => 0,
+            [factor] if factor.key == variable.key && !factor.divisor && factor.power > 0 => {
+                usize::try_from(factor.power).ok()?
+            }
+            _ => return None,
+        };
+        if power > MAX_DEGREE || matches!(term.coefficient, Num::Float(_)) {
+            return None;
+        }
+        if coefficients.len() <= power {
+            coefficients.resize(power + 1, Num::from(0));
+        }
+        coefficients[power] = term.coefficient;
+    }
+    Some((variable, coefficients))
+}
+
+/// Finds the rational roots of the polynomial with integer `coefficients`, from the constant term
+/// up, and divides the polynomial by the linear factor of each, e.g. `(x - 2)` for 2 or `(2x - 1)`
+/// for 1/2, so that what is left of `coefficients` has no rational roots. A root is listed once
+/// for each time it is divided out, and the smallest roots come first, e.g. 1 then -1 then 2.
+/// By the rational root theorem, a root `p/q` in lowest terms has `p` dividing the constant term
+/// and `q` dividing the leading coefficient. The search gives up when there are too many
+/// candidates to try, see `MAX_STEPS`.
+fn rational_roots(coefficients: &mut Vec<Num>) -> Vec<Rational> {
+    let mut roots = vec![];
+    let as_integer = |num: Num| match num {
+        Num::Rational(rational) => rational.to_i128(),
+        _ => None,
+    };
+    let ends = |coefficients: &[Num]| {
+        Some((
+            as_integer(coefficients[0])?,
+            as_integer(coefficients[coefficients.len() - 1])?,
+        ))
+    };
+    if coefficients.len() < 2 {
+        return roots;
+    }
+    let Some((last, leading)) = ends(coefficients) else {
+        return roots;
+    };
+    let (Some(mut numers), Some(mut denoms)) = (divisors(last), divisors(leading)) else {
+        return roots;
+    };
+    while coefficients.len() > 1 {
+        // Dividing by `qx - p` divides the constant term by `p` and the leading coefficient by
+        // `q`, so the divisors found at first are all that need to be tried.
+        let Some((last, leading)) = ends(coefficients) else {
+            break;
+        };
+        numers.retain(|numer| last % numer == 0);
+        denoms.retain(|denom| leading % denom == 0);
+        if 2 * numers.len() * denoms.len() * coefficients.len() > MAX_STEPS {
+            break;
+        }
+        let root = numers
+            .iter()
+            .flat_map(|numer| [*numer, -numer])
+            .flat_map(|numer| denoms.iter().map(move |denom| Rational::new(numer, *denom)))
+            .flatten()
+            .find(|root| {
+                let value = coefficients
+                    .iter()
+                    .rev()
+                    .fold(Num::from(0), |value, coefficient| {
+                        value * Num::Rational(*root) + *coefficient
+                    });
+                matches!(value, Num::Rational(_)) && value.is_zero()
+            });
+        let Some(root) = root else {
+            break;
+        };
+        // Dividing by `x - p/q` and then by `q` leaves integer coefficients.
+        let mut carry = Num::from(0);
+        let mut quotient = vec![Num::from(0); coefficients.len() - 1];
+        for power in (1..coefficients.len()).rev() {
+            carry = coefficients[power] + Num::Rational(root) * carry;
+            quotient[power - 1] = carry / integer(root.denom());
+        }
+        *coefficients = quotient;
+        roots.push(root);
+    }
+    roots.sort_by(|a, b| {
+        let size = |root: &Rational| (root.to_f64().abs(), root.is_negative());
+        size(a).partial_cmp(&size(b)).unwrap()
+    });
+    roots
+}
+
+/// The positive divisors of `n`, which is not 0, or `None` if it is too large to search.
+fn divisors(n: i128) -> Option<Vec<i128>> {
+    let n = n.abs();
+    if n == 0 || n > MAX_DIVIDEND {
+        return None;
+    }
+    let mut divisors = vec![];
+    let mut divisor = 1;
+    while divisor * divisor <= n {
+        if n % divisor == 0 {
+            divisors.push(divisor);
+            if divisor * divisor != n {
+                divisors.push(n / divisor);
+            }
+        }
+        divisor += 1;
+    }
+    divisors.sort();
+    Some(divisors)
+}
+
+/// The linear factor `q * variable - p` with the rational `root` `p/q`, e.g. `2x - 1` for 1/2.
+fn linear(variable: &Factor, root: Rational) -> Sum {
+    let leading = Term {
+        coefficient: integer(root.denom()),
+        factors: vec![variable.clone()],
+    };
+    add(vec![leading], constant(-integer(root.numer())))
+}
+
+fn integer(n: BigInt) -> Num {
+    Num::Rational(Rational::from_big(n.into()))
+}
+
+/// The polynomial in `variable` with `coefficients`, from the constant term up.
+fn polynomial(variable: &Factor, coefficients: &[Num]) -> Sum {
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, coefficient)| !coefficient.is_zero())
+        .map(|(power, coefficient)| Term {
+            coefficient: *coefficient,
+            factors: match power {
+                0 => vec![],
+                power => vec![Factor {
+                    power: power as i128,
+                    ..variable.clone()
+                }],
+            },
+        })
+        .collect()
+}
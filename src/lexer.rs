@@ -1,12 +1,20 @@
+use crate::error::ParseError;
+use crate::num::Num;
+use crate::token::Constant;
+use crate::token::Function;
 use crate::token::Operator::*;
+use crate::token::Span;
+use crate::token::Spanned;
 use crate::token::Token;
 use crate::token::Token::*;
 use crate::token::UnaryOperator;
+use crate::token::UnaryOperator::CommonLogarithm;
 use crate::token::Variable;
 use std::error::Error;
 
 pub struct Lexer {
-    pub list: Vec<Token>,
+    /// The tokens read from the input string, each with its byte span in that string.
+    pub list: Vec<Spanned<Token>>,
     pub ordering: Ordering,
 }
 
@@ -17,6 +25,160 @@ pub enum Ordering {
     Post,
 }
 
+/// Settings for how `Lexer::new_inorder_with` reads the input string.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct LexerOptions {
+    pub identifiers: Identifiers,
+    pub imaginary_unit: ImaginaryUnit,
+    pub implicit_multiplication: ImplicitMultiplication,
+}
+
+/// How tightly a multiplication written without an operator, e.g. `2x` or `(a+b)(a-b)`, binds.
+/// The lexer inserts an `Op(Multiply)` with an empty span for each one.
+/// An implicit multiplication is inserted after a number, variable, constant or `)` when it is
+/// directly followed by a variable, constant, `(`, function or unary operator, e.g. `x sin y`. Two
+/// numbers, e.g. `1 2`, are not multiplied.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ImplicitMultiplication {
+    /// The same as an explicit `*`, so `1/2x` is `(1/2)*x`.
+    #[default]
+    Standard,
+    /// Ahead of explicit `*` and `/`, so `1/2x` is `1/(2*x)`, the way formulas are usually written
+    /// by hand. Each run of implicitly multiplied factors is grouped in parentheses, which have
+    /// empty spans.
+    Tight,
+}
+
+/// The letter written for the imaginary unit, which is also used as a suffix for imaginary
+/// numbers, e.g. `4i`. The other letter is an ordinary variable.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ImaginaryUnit {
+    /// `i`, as in mathematics.
+    #[default]
+    I,
+    /// `j`, as in electrical engineering.
+    J,
+}
+
+impl ImaginaryUnit {
+    pub fn letter(&self) -> &'static str {
+        match self {
+            ImaginaryUnit::I => "i",
+            ImaginaryUnit::J => "j",
+        }
+    }
+}
+
+/// How a run of letters which is not a known word, e.g. `xy`, is read into variables.
+/// In both cases a variable may be followed by a subscript, e.g. `x_1` or `v_max`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Identifiers {
+    /// Every letter is a variable of its own, the way formulas are usually written, so `xy` is
+    /// the variables `x` and `y`. Known words are still read first, `sinx` is `sin x`.
+    #[default]
+    Letters,
+    /// A run of letters and digits is a single variable, the way programs are usually written,
+    /// so `xy`, `theta` and `rate2` are each one variable. A known word has to make up the whole
+    /// run, `sinx` is the variable `sinx`, and a run directly followed by `(` must be a known
+    /// function, so a typo like `sinq(x)` is an error.
+    Words,
+}
+
+// Note: Each word which is processed into a token other than a variable (e.g. "sin"), must be
+// listed here.
+// Words are matched longest first, so a run of letters like "sinx" is read as "sin" then "x", and
+// "sinh" is read as itself rather than "sin" then "h".
+pub(crate) const WORDS: &[(&str, Token)] = &[
+    ("sin", UnOp(UnaryOperator::Sine)),
+    ("cos", UnOp(UnaryOperator::Cosine)),
+    ("tan", UnOp(UnaryOperator::Tangent)),
+    ("csc", UnOp(UnaryOperator::Cosecant)),
+    ("sec", UnOp(UnaryOperator::Secant)),
+    ("cot", UnOp(UnaryOperator::Cotangent)),
+    ("arcsin", UnOp(UnaryOperator::ArcSine)),
+    ("asin", UnOp(UnaryOperator::ArcSine)),
+    ("arccos", UnOp(UnaryOperator::ArcCosine)),
+    ("acos", UnOp(UnaryOperator::ArcCosine)),
+    ("arctan", UnOp(UnaryOperator::ArcTangent)),
+    ("atan", UnOp(UnaryOperator::ArcTangent)),
+    ("arccsc", UnOp(UnaryOperator::ArcCosecant)),
+    ("acsc", UnOp(UnaryOperator::ArcCosecant)),
+    ("arcsec", UnOp(UnaryOperator::ArcSecant)),
+    ("asec", UnOp(UnaryOperator::ArcSecant)),
+    ("arccot", UnOp(UnaryOperator::ArcCotangent)),
+    ("acot", UnOp(UnaryOperator::ArcCotangent)),
+    ("sinh", UnOp(UnaryOperator::HyperbolicSine)),
+    ("cosh", UnOp(UnaryOperator::HyperbolicCosine)),
+    ("tanh", UnOp(UnaryOperator::HyperbolicTangent)),
+    ("asinh", UnOp(UnaryOperator::InverseHyperbolicSine)),
+    ("arsinh", UnOp(UnaryOperator::InverseHyperbolicSine)),
+    ("acosh", UnOp(UnaryOperator::InverseHyperbolicCosine)),
+    ("arcosh", UnOp(UnaryOperator::InverseHyperbolicCosine)),
+    ("atanh", UnOp(UnaryOperator::InverseHyperbolicTangent)),
+    ("artanh", UnOp(UnaryOperator::InverseHyperbolicTangent)),
+    ("ln", UnOp(UnaryOperator::NaturalLogarithm)),
+    ("log", UnOp(UnaryOperator::CommonLogarithm)),
+    ("log10", UnOp(UnaryOperator::CommonLogarithm)),
+    ("log2", UnOp(UnaryOperator::BinaryLogarithm)),
+    ("exp", UnOp(UnaryOperator::Exponential)),
+    ("sqrt", UnOp(UnaryOperator::SquareRoot)),
+    ("pi", Const(Constant::Pi)),
+    ("π", Const(Constant::Pi)),
+    ("e", Const(Constant::E)),
+    ("tau", Const(Constant::Tau)),
+    ("τ", Const(Constant::Tau)),
+    ("phi", Const(Constant::GoldenRatio)),
+    ("φ", Const(Constant::GoldenRatio)),
+    ("inf", Const(Constant::Infinity)),
+    ("max", Func(Function::Maximum)),
+    ("min", Func(Function::Minimum)),
+    ("atan2", Func(Function::ArcTangent2)),
+    ("hypot", Func(Function::Hypotenuse)),
+    ("clamp", Func(Function::Clamp)),
+];
+
+// The spelled out names of Greek letters, which are read as variables named by the letter itself,
+// e.g. "theta" is the same variable as "θ". Upper-case letters which look like a Latin letter,
+// e.g. "Alpha", are left out, as are "pi", "tau" and "phi", which are read as constants, like the
+// letters "π", "τ" and "φ" themselves.
+// With `Identifiers::Letters` a name is only read where the whole run of letters is made up of
+// names and words, so "xeta" is not read as "x" then "eta", and names of two letters, "mu", "nu"
+// and "xi", are not read at all, as "xi" is more likely to be "x" times "i".
+pub(crate) const GREEK: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("omicron", "ο"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("upsilon", "υ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+];
+
 impl Lexer {
     /// Takes input string and returns tokens.
     /// e.g.
@@ -31,65 +193,152 @@ impl Lexer {
     ///                          Op(Divide),
     ///                          Number(8.0)
     ///                      ]
-    pub fn new_inorder(s: &str) -> Result<Self, Box<dyn Error>> {
-        // Note: Each character which is processed into a struct (e.g. '+', 'x', 'y'), must be
-        // listed among the split chars here.
-        let split_chars = ['+', '-', '/', '*', '(', ')', 'x', 'y', 'z'];
-        let mut mid_split = s
-            .split_whitespace()
-            .flat_map(|split| split.split_inclusive(split_chars))
-            .flat_map(split_nums)
-            .flatten()
-            .filter_map(|split| {
-                if split.chars().nth(0)?.is_numeric() {
-                    Some(Number(
-                        split.parse::<f32>().expect("Could not parse number"),
-                    ))
-                } else {
-                    match split {
-                        "(" => Some(LParen),
-                        ")" => Some(RParen),
-                        "+" => Some(Op(Add)),
-                        "-" => Some(Op(Subtract)),
-                        "*" => Some(Op(Multiply)),
-                        "/" => Some(Op(Divide)),
-                        "x" => Some(Var(Variable::X)),
-                        "y" => Some(Var(Variable::Y)),
-                        "z" => Some(Var(Variable::Z)),
-                        "sin" => Some(UnOp(UnaryOperator::Sine)),
-                        "cos" => Some(UnOp(UnaryOperator::Cosine)),
-                        "tan" => Some(UnOp(UnaryOperator::Tangent)),
-                        _ => None,
+    /// Any part of the input which cannot be turned into a token is returned as a `ParseError`
+    /// with the span of the offending text.
+    /// Uses the default `LexerOptions`, see `Lexer::new_inorder_with`.
+    pub fn new_inorder(s: &str) -> Result<Self, ParseError> {
+        Lexer::new_inorder_with(s, LexerOptions::default())
+    }
+
+    /// Same as `Lexer::new_inorder`, with `options` deciding how the input is read, e.g. whether
+    /// `xy` is one variable or two.
+    pub fn new_inorder_with(s: &str, options: LexerOptions) -> Result<Self, ParseError> {
+        let mut chars = s.char_indices().peekable();
+        let mut mid_split: Vec<Spanned<Token>> = vec![];
+        while let Some((start, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let token = match c {
+                '(' => LParen,
+                ')' => RParen,
+                ',' => Comma,
+                '+' => Op(Add),
+                '-' => Op(Subtract),
+                '*' => match chars.next_if(|(_, next)| *next == '*') {
+                    Some(_) => {
+                        mid_split.push(Spanned::new(Op(Exponent), Span::new(start, start + 2)));
+                        continue;
                     }
+                    None => Op(Multiply),
+                },
+                '^' => Op(Exponent),
+                '/' => Op(Divide),
+                // Symbols pasted from typeset formulas.
+                '×' | '·' => Op(Multiply),
+                '÷' => Op(Divide),
+                '−' => Op(Subtract),
+                '√' => UnOp(UnaryOperator::SquareRoot),
+                '∞' => Const(Constant::Infinity),
+                // A metavariable in a rewrite rule, e.g. "?a", named by the whole word after '?'.
+                '?' => {
+                    let end = run_end(s, start + 1, |c| c.is_alphanumeric() || c == '_');
+                    if end == start + 1 {
+                        return Err(ParseError::UnknownSymbol {
+                            span: Span::new(start, start + 1),
+                        });
+                    }
+                    while chars.next_if(|(i, _)| *i < end).is_some() {}
+                    let variable = Variable::new(&s[start + 1..end]);
+                    mid_split.push(Spanned::new(Meta(variable), Span::new(start, end)));
+                    continue;
                 }
-            })
-            .enumerate()
-            .peekable();
-        let mut list: Vec<Token> = vec![];
-        while let Some((i, mut token)) = mid_split.next() {
-            if let Some((_, peeked)) = mid_split.peek() {
-                // Turn subtraction '-' to negation if first token and the next token is a valid
-                // target for negation.
-                if i == 0 && token == Op(Subtract)
-                    || (matches!(peeked, LParen)
-                        && matches!(peeked, Var(_))
-                        && matches!(peeked, UnOp(_))
-                        && matches!(peeked, Number(_)))
-                {
-                    token = UnOp(UnaryOperator::Negation);
-                } else if *peeked == Op(Subtract)
-                    && (matches!(token, Op(_)) || matches!(token, UnOp(_)))
-                {
-                    // Turn subtraction '-' to negation if it immediately follows a regular
-                    // (binary) operation.
-                    list.push(token);
-                    mid_split.next();
-                    token = UnOp(UnaryOperator::Negation);
+                c if c.is_ascii_digit() || c == '.' => {
+                    let (number, end) = read_number(s, start)?;
+                    while chars.next_if(|(i, _)| *i < end).is_some() {}
+                    let span = Span::new(start, end);
+                    // A number directly followed by the imaginary unit, e.g. "4i", is imaginary.
+                    let unit = options.imaginary_unit.letter();
+                    if s[end..].starts_with(unit)
+                        && !s[end + unit.len()..]
+                            .starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                    {
+                        chars.next();
+                        let span = Span::new(start, end + unit.len());
+                        mid_split.push(Spanned::new(Imaginary(number), span));
+                    } else {
+                        mid_split.push(Spanned::new(Number(number), span));
+                    }
+                    continue;
+                }
+                c if c.is_alphabetic() => {
+                    let end = match options.identifiers {
+                        Identifiers::Letters => {
+                            let end = run_end(s, start, char::is_alphabetic);
+                            end + word_digits(s, start, end)
+                        }
+                        Identifiers::Words => run_end(s, start, char::is_alphanumeric),
+                    };
+                    while chars.next_if(|(i, _)| *i < end).is_some() {}
+                    match options.identifiers {
+                        Identifiers::Letters => mid_split.extend(split_word(&s[start..end], start)),
+                        Identifiers::Words => mid_split.push(read_word(s, start, end)?),
+                    }
+
+                    // "log_b x" is the logarithm of x in base b, where b is a number or variable.
+                    // The base is moved in front, making the logarithm an infix operator,
+                    // "b log x".
+                    if mid_split.last().map(|token| token.value) == Some(UnOp(CommonLogarithm))
+                        && s[end..].starts_with('_')
+                    {
+                        let log = mid_split.pop().unwrap();
+                        chars.next();
+                        let base = log_base(s, end + 1, log.span)?;
+                        while chars.next_if(|(i, _)| *i < base.span.end).is_some() {}
+                        mid_split.push(base);
+                        mid_split.push(Spanned::new(
+                            Op(Logarithm),
+                            Span::new(log.span.start, base.span.start),
+                        ));
+                    } else if s[end..].starts_with('_') {
+                        let subscripted = subscript(s, end, mid_split.pop().unwrap())?;
+                        while chars.next_if(|(i, _)| *i < subscripted.span.end).is_some() {}
+                        mid_split.push(subscripted);
+                    }
+                    continue;
                 }
+                _ => {
+                    return Err(ParseError::UnknownSymbol {
+                        span: Span::new(start, start + c.len_utf8()),
+                    })
+                }
+            };
+            mid_split.push(Spanned::new(token, Span::new(start, start + c.len_utf8())));
+        }
+        if mid_split.is_empty() {
+            return Err(ParseError::EmptyInput {
+                span: Span::new(0, s.len()),
+            });
+        }
+
+        let mut list: Vec<Spanned<Token>> = vec![];
+        for (i, Spanned { mut value, span }) in mid_split.iter().copied().enumerate() {
+            if let Var(variable) = value {
+                if variable.name() == options.imaginary_unit.letter() {
+                    value = Imaginary(Num::from(1));
+                }
+            }
+            // Turn subtraction '-' to negation if it has no left operand, i.e. it is the first
+            // token or immediately follows an operation or an opening parenthesis, and there is
+            // something after it to negate.
+            if value == Op(Subtract)
+                && i + 1 < mid_split.len()
+                && matches!(
+                    list.last().map(|prev| prev.value),
+                    None | Some(Op(_)) | Some(UnOp(_)) | Some(Func(_)) | Some(LParen) | Some(Comma)
+                )
+            {
+                value = UnOp(UnaryOperator::Negation);
             }
-            list.push(token);
+            if implies_multiplication(list.last(), &mid_split[i..]) {
+                let end = list.last().unwrap().span.end;
+                list.push(Spanned::new(Op(Multiply), Span::new(end, end)));
+            }
+            list.push(Spanned::new(value, span));
+        }
+        if options.implicit_multiplication == ImplicitMultiplication::Tight {
+            list = group_implicit_products(list);
         }
-        //let list: Vec<Token> = mid_split.collect();
         Ok(Lexer {
             list,
             ordering: Ordering::In,
@@ -97,9 +346,8 @@ impl Lexer {
     }
 
     /// Consuming function that creates a pre-order list of tokens from an in-order list of tokens.
-    /// Currently of limited use as it does not handle parentheses.
-    // TODO: Decide whether to scrap this function or not. It would be more usefully handled by
-    // the tree struct in tree.rs.
+    /// Currently of limited use as it does not handle parentheses. Returns
+    /// `ParseError::WrongOrdering` when the list is not in-order.
     ///
     /// e.g.
     /// "3 * 2.0 + x"
@@ -118,29 +366,41 @@ impl Lexer {
     ///     Number(2.0),
     ///     Var(Variable::X)
     /// ]
-    pub fn in_to_pre(&mut self) {
+    // TODO: Decide whether to scrap this function or not. It would be more usefully handled by
+    // the tree struct in tree.rs.
+    pub fn in_to_pre(&mut self) -> Result<(), ParseError> {
         if self.ordering != Ordering::In {
-            panic!("The ordering must be in-order to use in_to_pre to change ordering");
+            let span = self
+                .list
+                .iter()
+                .map(|token| token.span)
+                .filter(|span| !span.is_empty())
+                .reduce(Span::join)
+                .unwrap_or(Span::new(0, 0));
+            return Err(ParseError::WrongOrdering { span });
         }
-        //let mut in_order: Vec<Token> = self.list.into_iter().rev().collect();
-        let mut pre_order: Vec<Token> = vec![];
-        let mut stack: Vec<Token> = vec![];
+        let mut pre_order: Vec<Spanned<Token>> = vec![];
+        let mut stack: Vec<Spanned<Token>> = vec![];
         for token in self.list.iter() {
-            match token {
-                Op(op) => {
-                    pre_order.push(Token::Op(*op));
+            match token.value {
+                Op(_) => {
+                    pre_order.push(*token);
                     while let Some(num_var) = stack.pop() {
                         pre_order.push(num_var);
                     }
                 }
-                UnOp(un_op) => {
-                    pre_order.push(Token::UnOp(*un_op));
-                    pre_order.push(stack.pop().unwrap());
+                UnOp(_) | Func(_) => {
+                    pre_order.push(*token);
+                    pre_order.push(
+                        stack
+                            .pop()
+                            .ok_or(ParseError::DanglingOperator { span: token.span })?,
+                    );
                 }
-                Number(n) => stack.push(Token::Number(*n)),
-                Var(v) => stack.push(Token::Var(*v)),
+                Number(_) | Imaginary(_) | Var(_) | Meta(_) | Const(_) => stack.push(*token),
                 LParen => (),
                 RParen => (),
+                Comma => (),
             }
         }
         if let Some(last_token) = stack.pop() {
@@ -149,9 +409,285 @@ impl Lexer {
 
         self.list = pre_order;
         self.ordering = Ordering::Pre;
+        Ok(())
+    }
+}
+
+// Returns the byte index just past the run of characters matching `pred` which starts at `start`.
+fn run_end(s: &str, start: usize, pred: impl Fn(char) -> bool) -> usize {
+    s[start..]
+        .char_indices()
+        .find(|(_, c)| !pred(*c))
+        .map_or(s.len(), |(i, _)| start + i)
+}
+
+// The length of the digits which follow the run of letters `start..end` when they end a word, as
+// in "log2", "log10" or "atan2". Otherwise the digits are a number of their own, e.g. "x2", and
+// the length is 0.
+fn word_digits(s: &str, start: usize, end: usize) -> usize {
+    let is_digit = |c: char| c.is_ascii_digit() || c == '.';
+    WORDS
+        .iter()
+        .filter_map(|(name, _)| {
+            let letters = name.trim_end_matches(|c: char| c.is_ascii_digit());
+            let digits = &name[letters.len()..];
+            (!digits.is_empty()
+                && s[start..end].ends_with(letters)
+                && s[end..].starts_with(digits)
+                && !s[end + digits.len()..].starts_with(is_digit))
+            .then_some(digits.len())
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+// Whether an implicit multiplication belongs between the token `prev` and `rest`, the tokens
+// after it, e.g. "2x" or "(a+b)(a-b)". A number only starts an implicit product as the base of a
+// logarithm, e.g. "2 log_3 x", so "x2" and "(a+b)2" are left for the tree to report as a missing
+// operator: a number after an operand more likely means a subscript or a power than a product.
+fn implies_multiplication(prev: Option<&Spanned<Token>>, rest: &[Spanned<Token>]) -> bool {
+    let ends_operand = matches!(
+        prev.map(|prev| prev.value),
+        Some(Number(_) | Imaginary(_) | Const(_) | Var(_) | Meta(_) | RParen)
+    );
+    let starts_operand = match rest[0].value {
+        Var(_) | Meta(_) | Const(_) | LParen | UnOp(_) | Func(_) => true,
+        Number(_) => rest.get(1).is_some_and(|next| next.value == Op(Logarithm)),
+        _ => false,
+    };
+    ends_operand && starts_operand
+}
+
+// Wraps each run of factors joined by implicit multiplication in parentheses, so that the run is
+// multiplied out before any explicit operator around it, e.g. "1/2x" becomes "1/(2x)".
+fn group_implicit_products(list: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+    // A run ends at an operator binding no tighter than multiplication, a comma, or the
+    // parenthesis around it.
+    let ends_run = |token: &Spanned<Token>| match token.value {
+        Op(Add | Subtract | Divide) | Comma => true,
+        Op(Multiply) => !token.span.is_empty(),
+        _ => false,
+    };
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for (i, token) in list.iter().enumerate() {
+        if token.value != Op(Multiply) || !token.span.is_empty() {
+            continue;
+        }
+        let (mut first, mut depth) = (i, 0);
+        while first > 0 {
+            match list[first - 1].value {
+                RParen => depth += 1,
+                LParen if depth == 0 => break,
+                LParen => depth -= 1,
+                _ if depth == 0 && ends_run(&list[first - 1]) => break,
+                _ => (),
+            }
+            first -= 1;
+        }
+        let (mut last, mut depth) = (i, 0);
+        while last + 1 < list.len() {
+            match list[last + 1].value {
+                LParen => depth += 1,
+                RParen if depth == 0 => break,
+                RParen => depth -= 1,
+                _ if depth == 0 && ends_run(&list[last + 1]) => break,
+                _ => (),
+            }
+            last += 1;
+        }
+        if !runs.contains(&(first, last)) {
+            runs.push((first, last));
+        }
+    }
+
+    let mut grouped = vec![];
+    for (i, token) in list.iter().copied().enumerate() {
+        for _ in runs.iter().filter(|(first, _)| *first == i) {
+            let start = token.span.start;
+            grouped.push(Spanned::new(LParen, Span::new(start, start)));
+        }
+        grouped.push(token);
+        for _ in runs.iter().filter(|(_, last)| *last == i) {
+            let end = token.span.end;
+            grouped.push(Spanned::new(RParen, Span::new(end, end)));
+        }
+    }
+    grouped
+}
+
+// Reads the number literal starting at `start`, returning it with the index just past its end.
+// Literals are decimal, e.g. "12", "0.5" or ".5", optionally with an exponent, e.g. "6.02e23" or
+// "1E-9", or integers in hexadecimal, binary or octal, e.g. "0x1F", "0b1010" or "0o17". Digits may
+// be grouped with underscores, e.g. "1_000_000".
+// An 'e' or 'E' is only an exponent when directly followed by digits, optionally signed, so "2e3"
+// is 2000 but "2e" and "2e-x" are read with e as Euler's number.
+fn read_number(s: &str, start: usize) -> Result<(Num, usize), ParseError> {
+    let radix = match s.get(start..start + 2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0b" | "0B") => Some(2),
+        Some("0o" | "0O") => Some(8),
+        _ => None,
+    };
+    let (end, digits, number) = match radix {
+        Some(radix) => {
+            let end = run_end(s, start + 2, |c| c.is_alphanumeric() || c == '_');
+            let digits = &s[start + 2..end];
+            let number = Num::parse_integer(&digits.replace('_', ""), radix);
+            (end, digits, number)
+        }
+        None => {
+            let is_decimal = |c: char| c.is_ascii_digit() || c == '.' || c == '_';
+            let mut end = run_end(s, start, is_decimal);
+            if let Some(exponent) = s[end..].strip_prefix(['e', 'E']) {
+                let digits = exponent.trim_start_matches(['+', '-']);
+                if exponent.len() - digits.len() <= 1
+                    && digits.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    end = run_end(s, s.len() - digits.len(), is_decimal);
+                }
+            }
+            let digits = &s[start..end];
+            (end, digits, Num::parse_decimal(&digits.replace('_', "")))
+        }
+    };
+    // Underscores can only separate digits.
+    let grouped = digits
+        .split('_')
+        .all(|group| group.ends_with(|c: char| c.is_alphanumeric()))
+        && digits
+            .split('_')
+            .skip(1)
+            .all(|group| group.starts_with(|c: char| c.is_alphanumeric()));
+    match number {
+        Some(number) if grouped => Ok((number, end)),
+        _ => Err(ParseError::MalformedNumber {
+            span: Span::new(start, end),
+        }),
+    }
+}
+
+// Reads the run of letters and digits `start..end` as a single word, for `Identifiers::Words`.
+// Anything other than a known word is a variable, unless it is directly followed by a `(` as if it
+// were a function.
+fn read_word(s: &str, start: usize, end: usize) -> Result<Spanned<Token>, ParseError> {
+    let word = &s[start..end];
+    let span = Span::new(start, end);
+    match word_at(word, true).filter(|(len, _)| *len == word.len()) {
+        Some((_, token)) => Ok(Spanned::new(token, span)),
+        None if s[end..].starts_with('(') => Err(ParseError::UnknownSymbol { span }),
+        None => Ok(Spanned::new(Var(Variable::new(word)), span)),
+    }
+}
+
+// Adds the subscript which follows the '_' at `underscore` to the name of `variable`, e.g. "x_1"
+// or "v_max". The subscript is a run of letters and digits, and can have subscripts of its own,
+// so "a_b_c" is the single variable `a_b_c`.
+fn subscript(
+    s: &str,
+    underscore: usize,
+    variable: Spanned<Token>,
+) -> Result<Spanned<Token>, ParseError> {
+    let mut end = run_end(s, underscore + 1, char::is_alphanumeric);
+    while end > underscore + 1 && s[end..].starts_with('_') {
+        let next = run_end(s, end + 1, char::is_alphanumeric);
+        if next == end + 1 {
+            break;
+        }
+        end = next;
+    }
+    match variable.value {
+        Var(name) if end > underscore + 1 => Ok(Spanned::new(
+            Var(Variable::new(&format!(
+                "{}_{}",
+                name,
+                &s[underscore + 1..end]
+            ))),
+            Span::new(variable.span.start, end),
+        )),
+        _ => Err(ParseError::UnknownSymbol {
+            span: Span::new(underscore, underscore + 1),
+        }),
+    }
+}
+
+// Reads the base of a logarithm written "log_b", starting at `start` just past the '_'. The base
+// must be a number or a single variable, which can have a subscript, e.g. "log_x_1 y". `log_span`
+// is the span of the "log", for errors.
+fn log_base(s: &str, start: usize, log_span: Span) -> Result<Spanned<Token>, ParseError> {
+    let end = match s[start..].chars().next() {
+        Some(c) if c.is_ascii_digit() || c == '.' => {
+            run_end(s, start, |c| c.is_ascii_digit() || c == '.')
+        }
+        Some(c) if c.is_alphabetic() => run_end(s, start, char::is_alphabetic),
+        _ => start,
+    };
+    let span = Span::new(start, end);
+    let word = &s[start..end];
+    if word.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        let number = Num::parse_decimal(word).ok_or(ParseError::MalformedNumber { span })?;
+        return Ok(Spanned::new(Number(number), span));
+    }
+    match split_word(word, start).as_slice() {
+        [base @ Spanned { value: Var(_), .. }] if s[end..].starts_with('_') => {
+            subscript(s, end, *base)
+        }
+        [base @ Spanned { value: Var(_), .. }] => Ok(*base),
+        _ => Err(ParseError::UnknownSymbol {
+            span: log_span.join(span),
+        }),
+    }
+}
+
+// The longest word listed in `WORDS`, or in `GREEK` too when `greek` is set, which `s` starts
+// with, as its length and token.
+fn word_at(s: &str, greek: bool) -> Option<(usize, Token)> {
+    let words = WORDS.iter().map(|(name, token)| (*name, *token));
+    let greek = GREEK
+        .iter()
+        .filter(|_| greek)
+        .map(|(name, letter)| (*name, Var(Variable::new(letter))));
+    words
+        .chain(greek)
+        .filter(|(name, _)| s.starts_with(name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(name, token)| (name.len(), token))
+}
+
+// Split a run of letters into the words listed in `WORDS`, with every letter outside of a word
+// being a variable of its own, e.g. "sinxy" becomes [Sine, X, Y]. The names in `GREEK` are only
+// read when the whole run is made up of them and words, e.g. "costheta" but not "xeta", see
+// `GREEK`. Used for `Identifiers::Letters`.
+// `offset` is the position of the run in the input string, used for the spans.
+fn split_word(word: &str, offset: usize) -> Vec<Spanned<Token>> {
+    let named =
+        |s: &str| word_at(s, true).filter(|(len, token)| !matches!(token, Var(_)) || *len > 2);
+    let greek = {
+        let mut i = 0;
+        while let Some((len, _)) = named(&word[i..]) {
+            i += len;
+        }
+        i == word.len()
+    };
+    let mut tokens: Vec<Spanned<Token>> = vec![];
+    let mut i = 0;
+    while i < word.len() {
+        let rest = &word[i..];
+        let found = match greek {
+            true => named(rest),
+            false => word_at(rest, false),
+        };
+        let (len, token) = match found {
+            Some(word) => word,
+            None => {
+                let letter = rest.chars().next().unwrap();
+                let name = &rest[..letter.len_utf8()];
+                (name.len(), Var(Variable::new(name)))
+            }
+        };
+        tokens.push(Spanned::new(token, Span::new(offset + i, offset + i + len)));
+        i += len;
     }
-    // TODO: Add error types for token that carry more information about the type of error
-    // encountered, e.g. "Divide by zero", "Operator not followed by a number or variable", etc.
+    tokens
 }
 
 // Split numbers from variables, e.g. 132x becomes ['132', 'x'], or (132) becomes ['(', '132', ')']
@@ -188,10 +724,8 @@ pub fn split_nums(s: &str) -> Result<Vec<&str>, Box<dyn Error>> {
         }
         Ok(splits_vec)
     } else if s.len() <= 1 {
-        return Ok(vec![s]);
+        Ok(vec![s])
     } else {
-        return Err(
-            "Malformed input string for split_nums. Input should be numbers or variables".into(),
-        );
+        Err("Malformed input string for split_nums. Input should be numbers or variables".into())
     }
 }
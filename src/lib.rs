@@ -1,5 +1,12 @@
+pub mod bindings;
+pub mod diagnostic;
+pub mod error;
+pub mod expand;
 pub mod expr;
+pub mod factor;
 pub mod lexer;
+pub mod num;
+pub mod simplify;
 pub mod token;
 pub mod tree;
 
@@ -0,0 +1,827 @@
+// Numbers stored in tokens, kept as exact fractions for as long as possible.
+
+use crate::token::Constant;
+use crate::token::UnaryOperator;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Signed;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::sync::Mutex;
+
+/// A number, either an exact fraction or a float.
+/// Numbers read from the input are rational, e.g. `0.1` is exactly 1/10, and stay rational through
+/// addition, subtraction, multiplication, division and integer powers, so `1/3 + 1/6` is exactly
+/// 1/2, however large the numerator and denominator get. Anything else, e.g. `2^0.5`, falls back
+/// to a float, as does an integer power too large to work out, see `Num::pow`.
+#[derive(Debug, Copy, Clone)]
+pub enum Num {
+    Rational(Rational),
+    Float(f64),
+}
+
+/// An exact fraction of any size, always stored in lowest terms with a positive denominator.
+/// A fraction whose numerator and denominator fit in an `i128` is stored as it is, and a larger one
+/// is interned in a table, like the names of variables, so that `Num` and `Token` can stay `Copy`.
+/// Every fraction has only one representation, so two fractions are equal exactly when their
+/// representations are.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational(Repr);
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Repr {
+    Small { numer: i128, denom: i128 },
+    // The index in `BIG` of a fraction which does not fit in `Small`.
+    Big(u32),
+}
+
+// The fractions too large to be stored inline, indexed by their `Repr::Big`, and the index of
+// each. They are kept for as long as the program runs, which is fine as each distinct fraction is
+// only ever stored once.
+struct Interned {
+    fractions: Vec<BigRational>,
+    indices: BTreeMap<BigRational, u32>,
+}
+
+static BIG: Mutex<Interned> = Mutex::new(Interned {
+    fractions: Vec::new(),
+    indices: BTreeMap::new(),
+});
+
+// Runs `f` on the table of large fractions.
+fn with_big<R>(f: impl FnOnce(&mut Interned) -> R) -> R {
+    let mut big = BIG.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut big)
+}
+
+// Integer powers whose numerator and denominator together would have more bits than this are not
+// worked out exactly, as they would take too long and too much memory, e.g. `2^100000000`.
+const MAX_POWER_BITS: u128 = 1 << 20;
+
+impl Rational {
+    /// The fraction `numer / denom` in lowest terms, or `None` if `denom` is 0.
+    pub fn new(numer: i128, denom: i128) -> Option<Rational> {
+        if denom == 0 {
+            return None;
+        }
+        let divisor = gcd(numer, denom);
+        let (numer, denom) = (numer / divisor, denom / divisor);
+        match (denom < 0, numer.checked_neg(), denom.checked_neg()) {
+            (false, _, _) => Some(Rational(Repr::Small { numer, denom })),
+            (true, Some(numer), Some(denom)) => Some(Rational(Repr::Small { numer, denom })),
+            (true, _, _) => Some(Rational::from_big(BigRational::new(
+                numer.into(),
+                denom.into(),
+            ))),
+        }
+    }
+
+    /// The fraction `value`, which `BigRational` always keeps in lowest terms.
+    pub fn from_big(value: BigRational) -> Rational {
+        if let (Some(numer), Some(denom)) = (value.numer().to_i128(), value.denom().to_i128()) {
+            return Rational(Repr::Small { numer, denom });
+        }
+        with_big(|big| {
+            if let Some(index) = big.indices.get(&value) {
+                return Rational(Repr::Big(*index));
+            }
+            let index = u32::try_from(big.fractions.len()).expect("too many large fractions");
+            big.fractions.push(value.clone());
+            big.indices.insert(value, index);
+            Rational(Repr::Big(index))
+        })
+    }
+
+    pub fn to_big(&self) -> BigRational {
+        match self.0 {
+            Repr::Small { numer, denom } => BigRational::new_raw(numer.into(), denom.into()),
+            Repr::Big(index) => with_big(|big| big.fractions[index as usize].clone()),
+        }
+    }
+
+    pub fn numer(&self) -> BigInt {
+        match self.0 {
+            Repr::Small { numer, .. } => numer.into(),
+            Repr::Big(_) => self.to_big().numer().clone(),
+        }
+    }
+
+    pub fn denom(&self) -> BigInt {
+        match self.0 {
+            Repr::Small { denom, .. } => denom.into(),
+            Repr::Big(_) => self.to_big().denom().clone(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self.0 {
+            Repr::Small { denom, .. } => denom == 1,
+            Repr::Big(_) => self.to_big().is_integer(),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == Repr::Small { numer: 0, denom: 1 }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self.0 {
+            Repr::Small { numer, .. } => numer < 0,
+            Repr::Big(_) => self.to_big().is_negative(),
+        }
+    }
+
+    /// The fraction as an `i128`, if it is an integer which fits in one.
+    pub fn to_i128(&self) -> Option<i128> {
+        match self.0 {
+            Repr::Small { numer, denom: 1 } => Some(numer),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self.0 {
+            Repr::Small { numer, denom } => numer as f64 / denom as f64,
+            Repr::Big(_) => self.to_big().to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn add(self, other: Rational) -> Rational {
+        if let (Repr::Small { numer: a, denom: b }, Repr::Small { numer: c, denom: d }) =
+            (self.0, other.0)
+        {
+            let small = || {
+                Rational::new(
+                    a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?,
+                    b.checked_mul(d)?,
+                )
+            };
+            if let Some(sum) = small() {
+                return sum;
+            }
+        }
+        Rational::from_big(self.to_big() + other.to_big())
+    }
+
+    fn mul(self, other: Rational) -> Rational {
+        if let (Repr::Small { numer: a, denom: b }, Repr::Small { numer: c, denom: d }) =
+            (self.0, other.0)
+        {
+            // Cancelling across first keeps the products small.
+            let (e, f) = (gcd(a, d), gcd(c, b));
+            let small = || Rational::new((a / e).checked_mul(c / f)?, (b / f).checked_mul(d / e)?);
+            if let Some(product) = small() {
+                return product;
+            }
+        }
+        Rational::from_big(self.to_big() * other.to_big())
+    }
+
+    fn neg(self) -> Rational {
+        match self.0 {
+            Repr::Small { numer, denom } if numer != i128::MIN => Rational(Repr::Small {
+                numer: -numer,
+                denom,
+            }),
+            _ => Rational::from_big(-self.to_big()),
+        }
+    }
+
+    fn recip(self) -> Option<Rational> {
+        match self.0 {
+            _ if self.is_zero() => None,
+            Repr::Small { numer, denom } => Rational::new(denom, numer),
+            Repr::Big(_) => Some(Rational::from_big(self.to_big().recip())),
+        }
+    }
+
+    /// The fraction raised to the integer `exponent`, or `None` for 0 to a negative power, or a
+    /// power too large to work out, see `MAX_POWER_BITS`.
+    fn pow(self, exponent: i128) -> Option<Rational> {
+        let base = if exponent < 0 { self.recip()? } else { self };
+        let exponent = exponent.unsigned_abs();
+        let one = Rational::new(1, 1).unwrap();
+        match base.to_i128() {
+            _ if exponent == 0 => return Some(one),
+            Some(0 | 1) => return Some(base),
+            Some(-1) if exponent.is_multiple_of(2) => return Some(one),
+            Some(-1) => return Some(base),
+            _ => {}
+        }
+        if let (Repr::Small { numer, denom }, Ok(exponent)) = (base.0, u32::try_from(exponent)) {
+            if let (Some(numer), Some(denom)) =
+                (numer.checked_pow(exponent), denom.checked_pow(exponent))
+            {
+                return Some(Rational(Repr::Small { numer, denom }));
+            }
+        }
+        let (numer, denom) = (base.numer(), base.denom());
+        let bits = u128::from(numer.bits() + denom.bits()).checked_mul(exponent)?;
+        if bits > MAX_POWER_BITS {
+            return None;
+        }
+        let exponent = u32::try_from(exponent).ok()?;
+        // The powers of a fraction in lowest terms are still in lowest terms.
+        Some(Rational::from_big(BigRational::new_raw(
+            numer.pow(exponent),
+            denom.pow(exponent),
+        )))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        if let (Repr::Small { numer: a, denom: b }, Repr::Small { numer: c, denom: d }) =
+            (self.0, other.0)
+        {
+            if let (Some(left), Some(right)) = (a.checked_mul(d), c.checked_mul(b)) {
+                return left.cmp(&right);
+            }
+        }
+        self.to_big().cmp(&other.to_big())
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Debug for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rational({}/{})", self.numer(), self.denom())
+    }
+}
+
+// The greatest common divisor, which is never 0 so it can always be divided by.
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i128::try_from(a).unwrap_or(1).max(1)
+}
+
+impl Num {
+    /// Reads a decimal literal made of digits with at most one `.`, e.g. `12`, `0.1` or `.5`, and
+    /// optionally an exponent, e.g. `6.02e23` or `1E-9`, as an exact fraction. Returns `None` if
+    /// `s` is not such a literal.
+    pub fn parse_decimal(s: &str) -> Option<Num> {
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(index) => (&s[..index], s[index + 1..].parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", whole, fraction);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let exact = || {
+            let numer = Rational::from_big(BigRational::from(digits.parse::<BigInt>().ok()?));
+            // The number is digits * 10^(exponent - number of fraction digits).
+            let shift = i64::from(exponent) - i64::try_from(fraction.len()).ok()?;
+            let scale = Rational::new(10, 1)?.pow(shift.into())?;
+            Some(numer.mul(scale))
+        };
+        match exact() {
+            Some(rational) => Some(Num::Rational(rational)),
+            None => s.parse().ok().map(Num::Float),
+        }
+    }
+
+    /// Reads an integer literal in the given `radix`, e.g. `1F` in base 16.
+    pub fn parse_integer(s: &str, radix: u32) -> Option<Num> {
+        if s.is_empty() || !s.chars().all(|c| c.is_digit(radix)) {
+            return None;
+        }
+        let integer = BigInt::parse_bytes(s.as_bytes(), radix)?;
+        Some(Num::Rational(Rational::from_big(integer.into())))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Num::Rational(rational) => rational.to_f64(),
+            Num::Float(float) => *float,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Num::Rational(rational) => rational.is_zero(),
+            Num::Float(float) => *float == 0.0,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Num::Rational(rational) => rational.is_negative(),
+            Num::Float(float) => *float < 0.0,
+        }
+    }
+
+    /// Raises the number to the power `exponent`, exactly when the base is rational and the
+    /// exponent an integer, unless the result would be too large to work out, e.g. `2^(10^9)`,
+    /// which gives a float.
+    pub fn pow(self, exponent: Num) -> Num {
+        let exact = match (self, exponent) {
+            (Num::Rational(base), Num::Rational(exponent)) => {
+                exponent.to_i128().and_then(|exponent| base.pow(exponent))
+            }
+            _ => None,
+        };
+        match exact {
+            Some(rational) => Num::Rational(rational),
+            None => Num::Float(self.to_f64().powf(exponent.to_f64())),
+        }
+    }
+}
+
+impl Add for Num {
+    type Output = Num;
+    fn add(self, other: Num) -> Num {
+        match (self, other) {
+            (Num::Rational(a), Num::Rational(b)) => Num::Rational(a.add(b)),
+            _ => Num::Float(self.to_f64() + other.to_f64()),
+        }
+    }
+}
+
+impl Sub for Num {
+    type Output = Num;
+    fn sub(self, other: Num) -> Num {
+        self + -other
+    }
+}
+
+impl Mul for Num {
+    type Output = Num;
+    fn mul(self, other: Num) -> Num {
+        match (self, other) {
+            (Num::Rational(a), Num::Rational(b)) => Num::Rational(a.mul(b)),
+            _ => Num::Float(self.to_f64() * other.to_f64()),
+        }
+    }
+}
+
+impl Div for Num {
+    type Output = Num;
+    fn div(self, other: Num) -> Num {
+        match (self, other) {
+            // Dividing by 0 gives an infinity or NaN, like floats.
+            (Num::Rational(a), Num::Rational(b)) => match b.recip() {
+                Some(b) => Num::Rational(a.mul(b)),
+                None => Num::Float(a.to_f64() / b.to_f64()),
+            },
+            _ => Num::Float(self.to_f64() / other.to_f64()),
+        }
+    }
+}
+
+impl Neg for Num {
+    type Output = Num;
+    fn neg(self) -> Num {
+        match self {
+            Num::Rational(rational) => Num::Rational(rational.neg()),
+            Num::Float(float) => Num::Float(-float),
+        }
+    }
+}
+
+// Rationals compare exactly, but as soon as a float is involved the comparison is between floats.
+impl PartialEq for Num {
+    fn eq(&self, other: &Num) -> bool {
+        match (self, other) {
+            (Num::Rational(a), Num::Rational(b)) => a == b,
+            _ => self.to_f64() == other.to_f64(),
+        }
+    }
+}
+
+impl PartialOrd for Num {
+    fn partial_cmp(&self, other: &Num) -> Option<Ordering> {
+        match (self, other) {
+            (Num::Rational(a), Num::Rational(b)) => Some(a.cmp(b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl From<i32> for Num {
+    fn from(integer: i32) -> Self {
+        Num::Rational(Rational::new(integer.into(), 1).unwrap())
+    }
+}
+
+/// A float is read back from its shortest decimal representation, so `Num::from(0.1)` is exactly
+/// 1/10 rather than the nearest `f64` to it.
+impl From<f64> for Num {
+    fn from(float: f64) -> Self {
+        if !float.is_finite() {
+            return Num::Float(float);
+        }
+        let decimal = float.abs().to_string();
+        match Num::parse_decimal(&decimal) {
+            Some(num) if float.is_sign_negative() => -num,
+            Some(num) => num,
+            None => Num::Float(float),
+        }
+    }
+}
+
+/// Prints the fraction exactly: as an integer, e.g. `3`, as a decimal when it has a finite decimal
+/// expansion, e.g. `0.25`, and otherwise as a fraction, e.g. `1/3`.
+/// With a precision, e.g. `{:.3}`, it is printed as a decimal rounded to that many places.
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(precision) = f.precision() {
+            return write!(f, "{:.*}", precision, self.to_f64());
+        }
+        let (numer, denom) = (self.numer(), self.denom());
+        if self.is_integer() {
+            return write!(f, "{}", numer);
+        }
+        // The number of decimal places is the larger power of 2 or 5 in the denominator.
+        let twos = denom.trailing_zeros().unwrap_or(0);
+        let (mut rest, mut fives) = (&denom >> twos, 0);
+        while (&rest % 5u32).is_zero() {
+            rest /= 5u32;
+            fives += 1;
+        }
+        if rest != BigInt::from(1) {
+            return write!(f, "{}/{}", numer, denom);
+        }
+        let places = twos.max(fives) as usize;
+        let scaled = numer * (BigInt::from(10).pow(places as u32) / denom);
+        let sign = if scaled.is_negative() { "-" } else { "" };
+        let digits = format!("{:0>width$}", scaled.abs(), width = places + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places);
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl Display for Num {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Num::Rational(rational) => Display::fmt(rational, f),
+            Num::Float(float) => Display::fmt(float, f),
+        }
+    }
+}
+
+/// A type which expressions can be evaluated in, so that evaluation can trade speed for exactness,
+/// e.g. `f64` for speed, or `Num` to keep fractions exact.
+/// Besides arithmetic, operations are done in `f64` and converted back, unless a type overrides
+/// them with something better.
+pub trait Numeric:
+    Copy
+    + Debug
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn from_f64(x: f64) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    /// Whether the number is NaN, e.g. the result of `0 * inf`.
+    fn is_nan(self) -> bool {
+        self.to_f64().is_nan()
+    }
+
+    /// Whether the number is infinite, e.g. the result of `exp(1000)`.
+    fn is_infinite(self) -> bool {
+        self.to_f64().is_infinite()
+    }
+
+    /// Converts a number read from the input.
+    fn from_num(num: Num) -> Self {
+        Self::from_f64(num.to_f64())
+    }
+
+    /// Converts a named constant.
+    fn from_constant(constant: Constant) -> Self {
+        Self::from_f64(constant.value())
+    }
+
+    /// Converts an imaginary number read from the input, e.g. `4i`. Types without imaginary
+    /// numbers give NaN, as they do for e.g. `sqrt(-1)`.
+    fn from_imaginary(_num: Num) -> Self {
+        Self::from_f64(f64::NAN)
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        Self::from_f64(self.to_f64().powf(exponent.to_f64()))
+    }
+
+    /// The logarithm of the number in the given `base`.
+    fn log(self, base: Self) -> Self {
+        Self::from_f64(self.to_f64().log(base.to_f64()))
+    }
+
+    /// Applies a unary operator, e.g. sine, to the number.
+    fn unary(self, op: UnaryOperator) -> Self {
+        match op {
+            UnaryOperator::Negation => -self,
+            _ => Self::from_f64(op.apply(self.to_f64())),
+        }
+    }
+}
+
+impl Numeric for f32 {
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self.into()
+    }
+}
+
+impl Numeric for f64 {
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Numeric for Num {
+    fn from_f64(x: f64) -> Self {
+        Num::Float(x)
+    }
+
+    fn to_f64(self) -> f64 {
+        Num::to_f64(&self)
+    }
+
+    /// Only a float is infinite, as a rational of any size is exact.
+    fn is_infinite(self) -> bool {
+        matches!(self, Num::Float(float) if float.is_infinite())
+    }
+
+    fn from_num(num: Num) -> Self {
+        num
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        Num::pow(self, exponent)
+    }
+}
+
+/// A complex number `re + im i`, for evaluating expressions where real numbers are not enough,
+/// e.g. `sqrt(-1)`, `e^(i*pi)` or `ln(-2)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    /// The absolute value, or distance from 0.
+    pub fn norm(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The angle from the positive real axis, between -π and π.
+    pub fn arg(&self) -> f64 {
+        // Adding 0 turns -0 into 0, so that negative real numbers, e.g. -(2 + 0i), have an angle
+        // of π rather than -π.
+        (self.im + 0.0).atan2(self.re)
+    }
+
+    pub fn exp(self) -> Complex {
+        let scale = self.re.exp();
+        Complex::new(scale * self.im.cos(), scale * self.im.sin())
+    }
+
+    /// The principal natural logarithm, with the imaginary part between -π and π.
+    pub fn ln(self) -> Complex {
+        Complex::new(self.norm().ln(), self.arg())
+    }
+
+    /// The principal square root, with a non-negative real part.
+    pub fn sqrt(self) -> Complex {
+        let norm = self.norm();
+        let re = ((norm + self.re) / 2.0).sqrt();
+        let im = ((norm - self.re) / 2.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    pub fn sin(self) -> Complex {
+        Complex::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    pub fn cos(self) -> Complex {
+        Complex::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    pub fn sinh(self) -> Complex {
+        Complex::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+
+    pub fn cosh(self) -> Complex {
+        Complex::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+
+    pub fn recip(self) -> Complex {
+        Complex::from_f64(1.0) / self
+    }
+
+    fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+
+    // The integer power `n` by repeated squaring.
+    fn powi(self, n: i32) -> Complex {
+        let (mut base, mut power) = (self, Complex::from_f64(1.0));
+        let mut exponent = n.unsigned_abs();
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                power = power * base;
+            }
+            base = base * base;
+            exponent /= 2;
+        }
+        match n < 0 {
+            true => power.recip(),
+            false => power,
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        if other.is_real() {
+            return Complex::new(self.re / other.re, self.im / other.re);
+        }
+        let denom = other.re * other.re + other.im * other.im;
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denom,
+            (self.im * other.re - self.re * other.im) / denom,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Numeric for Complex {
+    fn from_f64(x: f64) -> Self {
+        Complex::new(x, 0.0)
+    }
+
+    /// The real part, or NaN if the number is not real.
+    fn to_f64(self) -> f64 {
+        if self.is_real() {
+            self.re
+        } else {
+            f64::NAN
+        }
+    }
+
+    fn is_nan(self) -> bool {
+        self.re.is_nan() || self.im.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.re.is_infinite() || self.im.is_infinite()
+    }
+
+    fn from_imaginary(num: Num) -> Self {
+        Complex::new(0.0, num.to_f64())
+    }
+
+    fn pow(self, exponent: Complex) -> Complex {
+        // Real powers of real numbers are done in the reals where they can be, which is exact
+        // for e.g. 2^3, rather than going through the logarithm.
+        if self.is_real() && exponent.is_real() && (self.re >= 0.0 || exponent.re.fract() == 0.0) {
+            return Complex::from_f64(self.re.powf(exponent.re));
+        }
+        if self == Complex::from_f64(0.0) {
+            return Complex::from_f64(0.0);
+        }
+        // Integer powers are multiplied out, so that e.g. i^2 is exactly -1 rather than having
+        // the rounding error of cos(π) + i sin(π).
+        if exponent.is_real() && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64
+        {
+            return self.powi(exponent.re as i32);
+        }
+        (exponent * self.ln()).exp()
+    }
+
+    fn log(self, base: Complex) -> Complex {
+        self.ln() / base.ln()
+    }
+
+    fn unary(self, op: UnaryOperator) -> Complex {
+        use UnaryOperator::*;
+        let i = Complex::I;
+        let one = Complex::from_f64(1.0);
+        match op {
+            Negation => -self,
+            Sine => self.sin(),
+            Cosine => self.cos(),
+            Tangent => self.sin() / self.cos(),
+            Cosecant => self.sin().recip(),
+            Secant => self.cos().recip(),
+            Cotangent => self.cos() / self.sin(),
+            // asin z = -i ln(iz + sqrt(1 - z^2))
+            ArcSine => -i * (i * self + (one - self * self).sqrt()).ln(),
+            // acos z = -i ln(z + i sqrt(1 - z^2))
+            ArcCosine => -i * (self + i * (one - self * self).sqrt()).ln(),
+            // atan z = i/2 ln((i + z) / (i - z))
+            ArcTangent => i / Complex::from_f64(2.0) * ((i + self) / (i - self)).ln(),
+            ArcCosecant => self.recip().unary(ArcSine),
+            ArcSecant => self.recip().unary(ArcCosine),
+            ArcCotangent => self.recip().unary(ArcTangent),
+            HyperbolicSine => self.sinh(),
+            HyperbolicCosine => self.cosh(),
+            HyperbolicTangent => self.sinh() / self.cosh(),
+            // asinh z = ln(z + sqrt(z^2 + 1))
+            InverseHyperbolicSine => (self + (self * self + one).sqrt()).ln(),
+            // acosh z = ln(z + sqrt(z + 1) sqrt(z - 1))
+            InverseHyperbolicCosine => (self + (self + one).sqrt() * (self - one).sqrt()).ln(),
+            // atanh z = ln((1 + z) / (1 - z)) / 2
+            InverseHyperbolicTangent => ((one + self) / (one - self)).ln() / Complex::from_f64(2.0),
+            NaturalLogarithm => self.ln(),
+            CommonLogarithm => self.log(Complex::from_f64(10.0)),
+            BinaryLogarithm => self.log(Complex::from_f64(2.0)),
+            Exponential => self.exp(),
+            SquareRoot => self.sqrt(),
+        }
+    }
+}
+
+impl Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_real() {
+            return Display::fmt(&self.re, f);
+        }
+        if self.re != 0.0 {
+            Display::fmt(&self.re, f)?;
+            if self.im >= 0.0 {
+                write!(f, "+")?;
+            }
+        }
+        Display::fmt(&self.im, f)?;
+        write!(f, "i")
+    }
+}
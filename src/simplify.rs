@@ -0,0 +1,216 @@
+// Simplifying a tree with the basic algebraic identities and constant folding.
+
+use crate::error::EvalError;
+use crate::num::Num;
+use crate::token::Operator;
+use crate::token::Token;
+use crate::tree::Assumptions;
+use crate::tree::Rule;
+use crate::tree::Tree;
+use binary_tree_ds::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type TreeNodeRef<T> = Rc<RefCell<TreeNode<T>>>;
+
+// The identities used by `Tree::simplify`, see `Rule::parse_list`. Each one has to make the tree
+// smaller, or keep its size and remove a multiplication, see `cost`. The first rule which applies
+// is used, so e.g. `-?a * -?a` comes before `?a * ?a`, which would also match `-x * -x`.
+const IDENTITIES: &str = "
+# Negation
+-(-?a) => ?a
+?a + -?b => ?a - ?b
+?a - -?b => ?a + ?b
+
+# Addition and subtraction
+?a - ?a => 0
+?a + 0 => ?a
+?a - 0 => ?a
+0 + ?a => ?a
+0 - ?a => -?a
+
+# Multiplication and division
+-?a / -?a => 1 if ?a != 0
+-?a / ?a => -1 if ?a != 0
+?a / -?a => -1 if ?a != 0
+?a / ?a => 1 if ?a != 0
+-?a * -?a => ?a^2
+-?a * ?a => -?a^2
+?a * -?a => -?a^2
+?a * ?a => ?a^2
+?a * 1 => ?a
+1 * ?a => ?a
+?a * -1 => -?a
+-1 * ?a => -?a
+?a * 0 => 0
+0 * ?a => 0
+?a / 1 => ?a
+0 / ?a => 0 if ?a != 0
+
+# Powers
+?a^1 => ?a
+?a^0 => 1 if ?a != 0
+1^?a => 1
+
+# Exact values of functions, which are irrational for most arguments
+sin(0) => 0
+sin(pi/2) => 1
+sin(pi) => 0
+cos(0) => 1
+cos(pi/2) => 0
+cos(pi) => -1
+tan(0) => 0
+tan(pi) => 0
+asin(0) => 0
+atan(0) => 0
+sinh(0) => 0
+cosh(0) => 1
+tanh(0) => 0
+exp(0) => 1
+ln(1) => 0
+ln(e) => 1
+ln(exp(?a)) => ?a
+ln(e^?a) => ?a
+log(1) => 0
+log(10) => 1
+log2(1) => 0
+log2(2) => 1
+sqrt(0) => 0
+sqrt(1) => 1
+";
+
+thread_local! {
+    // The identities with their numbers folded, so that e.g. `-1` is the number -1, as it is in
+    // a folded tree, rather than the negation of 1.
+    static RULES: Vec<Rule> = Rule::parse_list(IDENTITIES)
+        .unwrap()
+        .iter()
+        .map(|rule| {
+            let folded = Rule::new(
+                Tree::new(fold(rule.pattern().root())),
+                Tree::new(fold(rule.replacement().root())),
+            );
+            rule.conditions()
+                .iter()
+                .fold(folded, |folded, condition| folded?.with_condition(condition.clone()))
+                .unwrap()
+        })
+        .collect();
+}
+
+impl Tree {
+    /// Simplifies the tree with the basic algebraic identities, e.g. `-(-a) => a`, `a + 0 => a`
+    /// or `a * a => a^2`, and by working out operations on numbers, e.g. `2 * 3 => 6`. Numbers
+    /// are only worked out when the result is exact, so `1/3` stays a fraction and `sqrt(2)` is
+    /// left as it is, while the functions have exact values at some constants, e.g.
+    /// `sin(pi) => 0`.
+    /// Identities which only hold where an expression is not 0, e.g. `a / a => 1`, are only
+    /// applied to numbers and constants, see `Tree::simplify_with` to make assumptions about the
+    /// variables. Neither is a sub-expression removed, e.g. by `a * 0 => 0` or `a - a => 0`,
+    /// unless it is known to have a value, so `(1/0) * 0` and `1/x - 1/x` are kept.
+    /// Simplifying always finishes, as each step makes the tree smaller, or keeps its size and
+    /// removes a multiplication.
+    pub fn simplify(&self) -> Tree {
+        self.simplify_with(&Assumptions::default())
+    }
+
+    /// Same as `Tree::simplify`, with the identities which only hold for expressions which are
+    /// not 0 also applied where `assumptions` show an expression is not 0, e.g. `x / x => 1` if
+    /// `x` is assumed to be nonzero.
+    pub fn simplify_with(&self, assumptions: &Assumptions) -> Tree {
+        let mut tree = Tree::new(fold(self.root()));
+        RULES.with(|rules| loop {
+            let current = cost(tree.root());
+            let next = rules.iter().find_map(|rule| {
+                tree.find_assuming(rule, assumptions)
+                    .into_iter()
+                    .find_map(|site| {
+                        let mut candidate = tree.clone();
+                        candidate.apply_at_assuming(rule, &site, assumptions);
+                        let candidate = Tree::new(fold(candidate.root()));
+                        (cost(candidate.root()) < current).then_some(candidate)
+                    })
+            });
+            match next {
+                Some(next) => tree = next,
+                None => return tree,
+            }
+        })
+    }
+}
+
+/// Works out every operation on numbers in the tree under `node_ref` where the result is exact,
+/// or one of the numbers is already a float, returning the folded tree. Operations without a
+/// value, e.g. `1 / 0`, are left as they are.
+pub(crate) fn fold(node_ref: &TreeNodeRef<Token>) -> TreeNodeRef<Token> {
+    let node = node_ref.borrow();
+    let left = node.left.as_ref().map(fold);
+    let right = node.right.as_ref().map(fold);
+    let number = |child: &TreeNodeRef<Token>| match child.borrow().value {
+        Token::Number(num) if child.borrow().is_leaf() => Some(num),
+        _ => None,
+    };
+    let folded = |result: Num, operands: &[Num]| {
+        let exact = matches!(result, Num::Rational(_))
+            || operands
+                .iter()
+                .any(|operand| matches!(operand, Num::Float(_)));
+        let defined = EvalError::check(node.value, operands, result, 0).is_ok();
+        (exact && defined).then_some(result)
+    };
+    let value = match node.value {
+        Token::Op(op) => match (
+            left.as_ref().and_then(number),
+            right.as_ref().and_then(number),
+        ) {
+            (Some(a), Some(b)) => folded(op.apply_num(a, b), &[a, b]),
+            _ => None,
+        },
+        Token::UnOp(un_op) => match right.as_ref().and_then(number) {
+            Some(x) => folded(un_op.apply_num(x), &[x]),
+            None => None,
+        },
+        Token::Func(function) => {
+            let arguments = right.as_ref().and_then(|right| {
+                Tree::arguments(right)
+                    .iter()
+                    .map(number)
+                    .collect::<Option<Vec<Num>>>()
+            });
+            arguments.and_then(|arguments| {
+                let floats: Vec<f64> = arguments.iter().map(Num::to_f64).collect();
+                let result = function.apply(&floats);
+                // The result of e.g. `max` is one of its arguments, which is exact.
+                match arguments
+                    .iter()
+                    .find(|argument| argument.to_f64() == result)
+                {
+                    Some(argument) => Some(*argument),
+                    None => folded(Num::Float(result), &arguments),
+                }
+            })
+        }
+        _ => None,
+    };
+    match value {
+        Some(num) => TreeNode::new_rc(Token::Number(num), None, None),
+        None => TreeNode::new_rc(node.value, left, right),
+    }
+}
+
+/// The size of the tree under `node_ref`, then the number of multiplications in it. Simplifying
+/// only takes steps which lower this, comparing the size first, so it can never go on forever.
+fn cost(node_ref: &TreeNodeRef<Token>) -> (usize, usize) {
+    let node = node_ref.borrow();
+    let own = usize::from(node.value == Token::Op(Operator::Multiply));
+    [&node.left, &node.right]
+        .into_iter()
+        .flatten()
+        .map(cost)
+        .fold(
+            (1, own),
+            |(size, products), (child_size, child_products)| {
+                (size + child_size, products + child_products)
+            },
+        )
+}
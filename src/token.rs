@@ -1,8 +1,78 @@
 // This is where we parse the string into a vec of tokens.
 
+use crate::num::Num;
+use crate::num::Numeric;
 use crate::token::Operator::*;
 use crate::token::Token::*;
+use std::fmt::Debug;
 use std::fmt::Display;
+use std::sync::Mutex;
+
+/// A byte range `start..end` into the input string a token or expression was read from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Whether the span covers no text. Tokens the lexer inserts which were not written, e.g. the
+    /// `*` of an implicit multiplication like `2x`, have an empty span.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn join(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// A value together with the span of the input string it was read from, e.g. a `Token` produced
+/// by the lexer.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Spanned { value, span }
+    }
+}
+
+impl<T: Display> Display for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+// Spanned tokens compare equal to bare tokens when the tokens match, wherever they came from.
+impl PartialEq<Token> for Spanned<Token> {
+    fn eq(&self, other: &Token) -> bool {
+        self.value == *other
+    }
+}
+
+impl PartialEq<Spanned<Token>> for Token {
+    fn eq(&self, other: &Spanned<Token>) -> bool {
+        *self == other.value
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// Tokens are the first internal representation of the input string.
@@ -11,21 +81,50 @@ pub enum Token {
     LParen,
     // Right paren
     RParen,
+    // Separates the arguments of a function, e.g. log(2, x)
+    Comma,
     // Operators, e.g. +, -, /
     Op(Operator),
     // Unary Operators, e.g. Sine, Cos
     UnOp(UnaryOperator),
-    // Numbers, e.g. 1.23, 2800000.0, e, pi
-    Number(f32),
-    // Variables, e.g. x, y, z
+    // Functions of any number of arguments, e.g. max(a, b, c), atan2(y, x)
+    Func(Function),
+    // Numbers, e.g. 1.23, 2800000.0, exact where possible, see `Num`
+    Number(Num),
+    // Imaginary numbers, e.g. 4i, or i itself, which is Imaginary(1)
+    Imaginary(Num),
+    // Mathematical constants, e.g. e, pi, kept symbolic until evaluated
+    Const(Constant),
+    // Variables, e.g. x, y, theta, v_0
     Var(Variable),
+    // Metavariables, e.g. ?a, standing for any sub-expression in the pattern of a rewrite rule,
+    // see `tree::Rule`
+    Meta(Variable),
 }
 
+impl TryFrom<Token> for f64 {
+    type Error = &'static str;
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        match token {
+            Token::Number(num) => Ok(num.to_f64()),
+            _ => Err("Invalid token cannot be parsed into a float."),
+        }
+    }
+}
+impl TryFrom<&Token> for f64 {
+    type Error = &'static str;
+    fn try_from(token: &Token) -> Result<Self, Self::Error> {
+        match token {
+            Token::Number(num) => Ok(num.to_f64()),
+            _ => Err("Invalid token cannot be parsed into a float."),
+        }
+    }
+}
 impl TryFrom<Token> for f32 {
     type Error = &'static str;
     fn try_from(token: Token) -> Result<Self, Self::Error> {
         match token {
-            Token::Number(float) => Ok(float),
+            Token::Number(num) => Ok(num.to_f64() as f32),
             _ => Err("Invalid token cannot be parsed into a float."),
         }
     }
@@ -34,7 +133,7 @@ impl TryFrom<&Token> for f32 {
     type Error = &'static str;
     fn try_from(token: &Token) -> Result<Self, Self::Error> {
         match token {
-            Token::Number(float) => Ok(*float),
+            Token::Number(num) => Ok(num.to_f64() as f32),
             _ => Err("Invalid token cannot be parsed into a float."),
         }
     }
@@ -46,24 +145,67 @@ impl Display for Token {
         let base_string = match self {
             LParen => String::from("("),
             RParen => String::from(")"),
+            Comma => String::from(","),
             Op(op) => match op {
                 Multiply => String::from("\\*"),
                 Divide => String::from("/"),
                 Add => String::from("+"),
                 Subtract => String::from("-"),
+                Exponent => String::from("^"),
+                Logarithm => String::from("log"),
             },
             UnOp(un_op) => match un_op {
                 Negation => String::from("-"),
                 Sine => String::from("sin"),
                 Cosine => String::from("cos"),
                 Tangent => String::from("tan"),
+                Cosecant => String::from("csc"),
+                Secant => String::from("sec"),
+                Cotangent => String::from("cot"),
+                ArcSine => String::from("arcsin"),
+                ArcCosine => String::from("arccos"),
+                ArcTangent => String::from("arctan"),
+                ArcCosecant => String::from("arccsc"),
+                ArcSecant => String::from("arcsec"),
+                ArcCotangent => String::from("arccot"),
+                HyperbolicSine => String::from("sinh"),
+                HyperbolicCosine => String::from("cosh"),
+                HyperbolicTangent => String::from("tanh"),
+                InverseHyperbolicSine => String::from("asinh"),
+                InverseHyperbolicCosine => String::from("acosh"),
+                InverseHyperbolicTangent => String::from("atanh"),
+                NaturalLogarithm => String::from("ln"),
+                CommonLogarithm => String::from("log"),
+                BinaryLogarithm => String::from("log2"),
+                Exponential => String::from("exp"),
+                SquareRoot => String::from("√"),
+            },
+            Func(function) => match function {
+                Function::Maximum => String::from("max"),
+                Function::Minimum => String::from("min"),
+                Function::ArcTangent2 => String::from("atan2"),
+                Function::Hypotenuse => String::from("hypot"),
+                Function::Clamp => String::from("clamp"),
             },
-            Number(n) => format!("{number:.prec$}", prec = 3, number = n),
-            Var(v) => match v {
-                Variable::X => String::from("x"),
-                Variable::Y => String::from("y"),
-                Variable::Z => String::from("z"),
+            // Numbers are printed exactly, unless given a precision, e.g. `{:.3}`.
+            Number(n) => match f.precision() {
+                Some(precision) => format!("{:.*}", precision, n),
+                None => n.to_string(),
             },
+            Imaginary(n) if *n == Num::from(1) => String::from("i"),
+            Imaginary(n) => match f.precision() {
+                Some(precision) => format!("{:.*}i", precision, n),
+                None => format!("{}i", n),
+            },
+            Const(constant) => match constant {
+                Constant::Pi => String::from("π"),
+                Constant::E => String::from("e"),
+                Constant::Tau => String::from("τ"),
+                Constant::GoldenRatio => String::from("φ"),
+                Constant::Infinity => String::from("∞"),
+            },
+            Var(v) => v.to_string(),
+            Meta(v) => format!("?{}", v),
         };
         write!(f, "{}", base_string)
     }
@@ -73,96 +215,373 @@ impl Token {
     pub fn is_op(&self) -> bool {
         matches!(self, Op(_))
     }
+
+    /// Precedence, associativity and arity if the token is an operator, `None` otherwise.
+    /// A comma is treated as the loosest binding binary operator, joining the arguments of a
+    /// function.
+    pub fn info(&self) -> Option<OpInfo> {
+        match self {
+            Op(op) => Some(op.info()),
+            UnOp(un_op) => Some(un_op.info()),
+            Func(function) => Some(function.info()),
+            Comma => Some(OpInfo {
+                precedence: 0,
+                associativity: Associativity::Left,
+                arity: 2,
+                associative: true,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// How a chain of operators of the same precedence groups.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Associativity {
+    /// `1 - 2 - 3` is `(1 - 2) - 3`.
+    Left,
+    /// `2^3^2` is `2^(3^2)`. Prefix unary operators are right associative, `- sin x` is
+    /// `-(sin x)`.
+    Right,
+    /// A chain must be parenthesized to have a meaning.
+    None,
+}
+
+/// The entry for an operator in the operator table, which decides how the operator groups with its
+/// neighbours both when building a tree and when printing it back out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OpInfo {
+    /// Operators of higher precedence bind tighter, e.g. `*` binds tighter than `+`.
+    pub precedence: u8,
+    pub associativity: Associativity,
+    /// The number of operands, 1 for prefix unary operators and 2 for infix binary operators.
+    pub arity: usize,
+    /// Whether `a op (b other c)` means the same as `(a op b) other c` for every `other` operator
+    /// of the same precedence, so the parentheses may be left out, e.g. `a + (b - c)` is
+    /// `a + b - c`, but `a - (b + c)` is not `a - b + c`.
+    pub associative: bool,
+}
+
+impl OpInfo {
+    /// Whether this operator, waiting for its right operand, takes the operand ahead of the
+    /// operator `next` which follows it, e.g. `*` followed by `+` in `1 * 2 + 3`, or the first `-`
+    /// in `1 - 2 - 3`.
+    pub fn binds_before(&self, next: &OpInfo) -> bool {
+        self.precedence > next.precedence
+            || (self.precedence == next.precedence && next.associativity != Associativity::Right)
+    }
+
+    /// Whether an operand which is itself an operation with info `child` has to be parenthesized
+    /// when printed as the left (`is_left`) or right operand of this operator.
+    pub fn needs_parens(&self, child: &OpInfo, is_left: bool) -> bool {
+        // A prefix unary operator on the right already binds its own operand, e.g. 2 * -x.
+        if child.arity == 1 && !is_left {
+            return false;
+        }
+        if self.precedence != child.precedence {
+            return self.precedence > child.precedence;
+        }
+        match self.associativity {
+            Associativity::Left => !is_left && !self.associative,
+            Associativity::Right => is_left,
+            Associativity::None => true,
+        }
+    }
 }
 
-impl Priority for Token {
-    fn priority(&self) -> isize {
+/// A named mathematical constant. Constants stay symbolic in the tree, so that e.g. `sin(pi)` is
+/// known to be exactly 0, and are only turned into numbers with `Constant::value` when evaluating.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constant {
+    /// The ratio of a circle's circumference to its diameter, `pi` or `π`.
+    Pi,
+    /// Euler's number, the base of the natural logarithm, `e`.
+    E,
+    /// The ratio of a circle's circumference to its radius, `tau` or `τ`, which is 2π.
+    Tau,
+    /// The golden ratio, `phi` or `φ`, which is (1 + √5) / 2.
+    GoldenRatio,
+    /// `inf` or `∞`.
+    Infinity,
+}
+
+impl Constant {
+    pub fn value(&self) -> f64 {
         match self {
-            LParen => 1,
-            RParen => -1,
-            Op(op) => {
-                match op {
-                    Add => 2,
-                    Subtract => 2,
-                    Multiply => 3,
-                    Divide => 3,
-                    // more here
-                }
-            }
-            // Unary operators should all have priority above all regular (binary) operators
-            UnOp(_) => 10,
-            Number(_) => -1,
-            Var(_) => -1,
+            Constant::Pi => std::f64::consts::PI,
+            Constant::E => std::f64::consts::E,
+            Constant::Tau => std::f64::consts::TAU,
+            Constant::GoldenRatio => 1.618_033_988_749_895,
+            Constant::Infinity => f64::INFINITY,
         }
     }
 }
 
-pub(crate) trait Priority {
-    // TODO: Make return type Option<isize> and see if anything breaks.
-    // Currently -1 is taking the place of None, not great practice but I don't want to do all the
-    // unwraps right now.
-    fn priority(&self) -> isize;
+/// A variable, e.g. `x`, `theta` or `v_0`.
+/// Variables are interned symbols: the name is stored once in a table shared by the whole program,
+/// and the variable is just its index in that table, which keeps `Token` small and `Copy`. Two
+/// variables with the same name are always equal.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Variable(u32);
+
+// The names of all variables, indexed by their symbol. Names are leaked so they can be handed out
+// as `&'static str`, which is fine as each distinct name is only ever stored once.
+static SYMBOLS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+// Runs `f` on the symbol table, which always starts with the classic variables x, y and z.
+fn with_symbols<R>(f: impl FnOnce(&mut Vec<&'static str>) -> R) -> R {
+    let mut symbols = SYMBOLS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if symbols.is_empty() {
+        symbols.extend(["x", "y", "z"]);
+    }
+    f(&mut symbols)
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-// This is placeholder for now - we could change the approach to something else,
-// e.g. We could get rid of this type and just have Token::Var(u8), where Var(0) is the first
-// variable (maybe x), and Var(1) is the second variable in the expression "( 2x - y ) / 4x"
-// When adding a new variable, remember to modify the lexer function, "new_inorder()" variable
-// split_chars to include whatever character represents the variable.
-pub enum Variable {
-    X,
-    Y,
-    Z,
-    // more here
+impl Variable {
+    pub const X: Variable = Variable(0);
+    pub const Y: Variable = Variable(1);
+    pub const Z: Variable = Variable(2);
+
+    /// The variable called `name`, interning the name if it has not been seen before.
+    pub fn new(name: &str) -> Variable {
+        with_symbols(|symbols| {
+            let index = match symbols.iter().position(|symbol| *symbol == name) {
+                Some(index) => index,
+                None => {
+                    symbols.push(Box::leak(name.to_owned().into_boxed_str()));
+                    symbols.len() - 1
+                }
+            };
+            Variable(index as u32)
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        with_symbols(|symbols| symbols[self.0 as usize])
+    }
+}
+
+impl Debug for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Variable({:?})", self.name())
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// An enum for the different operator types our parser can handle.
 // This enum is subject to change, as it may be better to have the operators split into binary
 // operators (e.g. Multiply, Divide) and unary (e.g. Sine, Cosine).
-// When adding a new operator, remember to modify the lexer function, "new_inorder()" variable
-// split_chars to include whatever character represents the operator.
+// When adding a new operator, remember to modify the lexer function, "new_inorder()" to match
+// whatever character represents the operator.
 pub enum Operator {
     Multiply,
     Divide,
     Add,
     Subtract,
-    //Sine,
-    //Cosine,
-    //Tangent,
-    //ArcSine,
-    //ArcCosine,
-    //ArcTangent,
-    //Exponent,
-    //Logarithm,
+    Exponent,
+    /// The logarithm of the right operand in the base of the left operand, written `log(b, x)` or
+    /// `log_b x`.
+    Logarithm,
     // more here
 }
 
-impl Priority for Operator {
-    fn priority(&self) -> isize {
+impl Operator {
+    /// Applies the operator to its left and right operands.
+    pub fn apply(&self, left: f64, right: f64) -> f64 {
         match self {
-            Add => 2,
-            Subtract => 2,
-            Multiply => 3,
-            Divide => 3,
+            Multiply => left * right,
+            Divide => left / right,
+            Add => left + right,
+            Subtract => left - right,
+            Exponent => left.powf(right),
+            Logarithm => right.log(left),
+        }
+    }
+
+    /// Applies the operator to numbers of any `Numeric` type. For `Num` the result is kept
+    /// exact where the operator and operands allow it, e.g. `1/3 + 1/6` is exactly 1/2.
+    pub fn apply_num<N: Numeric>(&self, left: N, right: N) -> N {
+        match self {
+            Multiply => left * right,
+            Divide => left / right,
+            Add => left + right,
+            Subtract => left - right,
+            Exponent => left.pow(right),
+            Logarithm => right.log(left),
+        }
+    }
+
+    /// The operator table for binary operators.
+    pub fn info(&self) -> OpInfo {
+        let (precedence, associativity, associative) = match self {
+            Add => (2, Associativity::Left, true),
+            Subtract => (2, Associativity::Left, false),
+            Multiply => (3, Associativity::Left, true),
+            Divide => (3, Associativity::Left, false),
+            Exponent => (5, Associativity::Right, false),
+            // Only ever written as a function, so binds tighter than everything else.
+            Logarithm => (11, Associativity::Right, false),
             // more here
+        };
+        OpInfo {
+            precedence,
+            associativity,
+            arity: 2,
+            associative,
         }
     }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
+// When adding a new unary operator, remember to add its name to the lexer's `WORDS`.
 pub enum UnaryOperator {
     Negation,
     Sine,
     Cosine,
     Tangent,
+    Cosecant,
+    Secant,
+    Cotangent,
+    ArcSine,
+    ArcCosine,
+    ArcTangent,
+    ArcCosecant,
+    ArcSecant,
+    ArcCotangent,
+    HyperbolicSine,
+    HyperbolicCosine,
+    HyperbolicTangent,
+    InverseHyperbolicSine,
+    InverseHyperbolicCosine,
+    InverseHyperbolicTangent,
+    NaturalLogarithm,
+    /// The base 10 logarithm, `log x`. Given two arguments, `log(b, x)`, it becomes the
+    /// `Operator::Logarithm` of `x` in base `b`.
+    CommonLogarithm,
+    BinaryLogarithm,
+    Exponential,
+    SquareRoot,
+}
+
+impl UnaryOperator {
+    /// The operator table for unary operators.
+    /// Functions like sine bind tighter than all regular (binary) operators, so that `sin x ^ 2`
+    /// is `(sin x)^2`. Negation sits just below `Exponent`, so `-x^2` is `-(x^2)`, but above the
+    /// other binary operators.
+    pub fn info(&self) -> OpInfo {
+        let precedence = match self {
+            UnaryOperator::Negation => 4,
+            _ => 10,
+        };
+        OpInfo {
+            precedence,
+            associativity: Associativity::Right,
+            arity: 1,
+            associative: false,
+        }
+    }
 }
 
-impl Priority for UnaryOperator {
-    /// Operator priority for unary operators.
-    /// This should always be higher than
-    fn priority(&self) -> isize {
-        10
+impl UnaryOperator {
+    /// Applies the operator to a number. Angles are in radians, and inputs outside of the domain
+    /// of the function, e.g. `arcsin 2`, give `NaN`.
+    pub fn apply(&self, x: f64) -> f64 {
+        use UnaryOperator::*;
+        match self {
+            Negation => -x,
+            Sine => x.sin(),
+            Cosine => x.cos(),
+            Tangent => x.tan(),
+            Cosecant => x.sin().recip(),
+            Secant => x.cos().recip(),
+            Cotangent => x.tan().recip(),
+            ArcSine => x.asin(),
+            ArcCosine => x.acos(),
+            ArcTangent => x.atan(),
+            ArcCosecant => x.recip().asin(),
+            ArcSecant => x.recip().acos(),
+            ArcCotangent => x.recip().atan(),
+            HyperbolicSine => x.sinh(),
+            HyperbolicCosine => x.cosh(),
+            HyperbolicTangent => x.tanh(),
+            InverseHyperbolicSine => x.asinh(),
+            InverseHyperbolicCosine => x.acosh(),
+            InverseHyperbolicTangent => x.atanh(),
+            NaturalLogarithm => x.ln(),
+            CommonLogarithm => x.log10(),
+            BinaryLogarithm => x.log2(),
+            Exponential => x.exp(),
+            SquareRoot => x.sqrt(),
+        }
+    }
+
+    /// Applies the operator to a number of any `Numeric` type. Only negation keeps a `Num`
+    /// exact, everything else gives a float.
+    pub fn apply_num<N: Numeric>(&self, x: N) -> N {
+        x.unary(*self)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// Functions taking a comma separated list of arguments, e.g. `max(a, b, c)`.
+/// In a tree the arguments hang off the right of the function node, joined by `Token::Comma`
+/// nodes, see `Tree::arguments`.
+// When adding a new function, remember to add its name to the lexer's `WORDS`.
+pub enum Function {
+    /// The largest of one or more arguments.
+    Maximum,
+    /// The smallest of one or more arguments.
+    Minimum,
+    /// The angle of the point `(x, y)` from the positive x axis, written `atan2(y, x)`.
+    ArcTangent2,
+    /// The length of the hypotenuse of a right triangle, `hypot(a, b)` is `sqrt(a^2 + b^2)`.
+    Hypotenuse,
+    /// `clamp(x, lo, hi)` restricts `x` to the interval from `lo` to `hi`.
+    Clamp,
+}
+
+impl Function {
+    /// Whether the function can be applied to `count` arguments.
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Function::Maximum | Function::Minimum => count >= 1,
+            Function::ArcTangent2 | Function::Hypotenuse => count == 2,
+            Function::Clamp => count == 3,
+        }
+    }
+
+    /// Applies the function to its arguments, which must be a number of arguments the function
+    /// `accepts`. Any argument which is NaN, e.g. the real part of `i` when evaluating over
+    /// `Complex`, gives NaN, rather than being skipped by e.g. `max`.
+    pub fn apply(&self, args: &[f64]) -> f64 {
+        if args.iter().any(|arg| arg.is_nan()) {
+            return f64::NAN;
+        }
+        match self {
+            Function::Maximum => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Function::Minimum => args.iter().copied().fold(f64::INFINITY, f64::min),
+            Function::ArcTangent2 => args[0].atan2(args[1]),
+            Function::Hypotenuse => args[0].hypot(args[1]),
+            Function::Clamp => args[0].max(args[1]).min(args[2]),
+        }
+    }
+
+    /// The operator table entry for functions. A function is a prefix operator on its
+    /// parenthesized list of arguments, binding as tightly as the unary functions like sine.
+    pub fn info(&self) -> OpInfo {
+        OpInfo {
+            precedence: 10,
+            associativity: Associativity::Right,
+            arity: 1,
+            associative: false,
+        }
     }
 }
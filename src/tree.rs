@@ -1,87 +1,724 @@
+use crate::bindings::Bindings;
+use crate::error::EvalError;
+use crate::error::ParseError;
+use crate::error::TreeError;
+use crate::lexer::Lexer;
 use crate::lexer::Ordering;
-use crate::token::Priority;
+use crate::num::Num;
+use crate::num::Numeric;
+use crate::token::Operator;
+use crate::token::Span;
+use crate::token::Spanned;
 use crate::token::Token;
+use crate::token::UnaryOperator;
+use crate::token::Variable;
 use binary_tree_ds::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-type TreeNodeRef<T: Sized + Copy> = Rc<RefCell<TreeNode<T>>>;
+type TreeNodeRef<T> = Rc<RefCell<TreeNode<T>>>;
+// The indices of the first and last tokens an expression was built from.
+type TokenRange = (usize, usize);
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Tree {
     root: TreeNodeRef<Token>,
+    // The span of the input string each node was built from, when the tree was built from
+    // spanned tokens. Holding the node keeps its address from being reused by another node.
+    spans: Vec<(TreeNodeRef<Token>, Span)>,
+}
+
+// Two trees are equal when they represent the same expression, wherever they were read from.
+impl PartialEq for Tree {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+}
+
+/// The result of `Tree::eval`: a number when every variable has a value, or else the tree with
+/// everything that could be worked out replaced by its value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Num),
+    Partial(Tree),
+}
+
+/// A rewrite rule, replacing a sub-expression matching `pattern` with `replacement`, e.g. the
+/// pattern `?a * (?b + ?c)` with the replacement `?a * ?b + ?a * ?c`.
+/// A metavariable, `Token::Meta`, in the pattern matches any sub-expression, and must match the
+/// same sub-expression everywhere it appears, so `?a - ?a` matches `(x + 1) - (x + 1)` but not
+/// `x - 1`. In the replacement it stands for the sub-expression it matched. Every other token has
+/// to match exactly.
+/// A rule can also have conditions on what its metavariables matched, e.g. `?a != 0`, and is
+/// only applied where all of them hold. A rule whose replacement drops a metavariable, e.g.
+/// `?a * 0 => 0`, is only applied where what it matched is known to have a value, see
+/// `Assumptions::is_defined`, so that e.g. `(1 / x) * 0` is not rewritten to 0.
+/// Rules can be written as text and read with `Rule::parse`, e.g.
+/// `?a * (?b + ?c) => ?a*?b + ?a*?c` or `?a / ?a => 1 if ?a != 0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pattern: TreeNodeRef<Token>,
+    replacement: TreeNodeRef<Token>,
+    conditions: Vec<Condition>,
+}
+
+/// A comparison between two expressions which has to hold for a `Rule` to be applied, e.g.
+/// `?a != 0`. The metavariables are replaced by what they matched, and the condition only holds
+/// when both sides then evaluate to numbers which compare as required, or it is known from the
+/// `Assumptions` that an expression is not 0, or both sides are the same expression for `==`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub left: Tree,
+    pub comparison: Comparison,
+    pub right: Tree,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==` or `=`
+    Equal,
+    /// `!=` or `≠`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=` or `≤`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=` or `≥`
+    GreaterOrEqual,
 }
 
+// How each comparison is written in a rule, with the longest symbols first so that "<=" is not
+// read as "<".
+const COMPARISONS: &[(&str, Comparison)] = &[
+    ("==", Comparison::Equal),
+    ("!=", Comparison::NotEqual),
+    ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual),
+    ("≠", Comparison::NotEqual),
+    ("≤", Comparison::LessOrEqual),
+    ("≥", Comparison::GreaterOrEqual),
+    ("=", Comparison::Equal),
+    ("<", Comparison::Less),
+    (">", Comparison::Greater),
+];
+
+/// What may be assumed about the variables of an expression when rewriting it, e.g. that `x` is
+/// not 0, so that `x / x` can be rewritten to 1. Nothing is assumed by default.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Assumptions {
+    /// Variables which are not 0.
+    pub nonzero: Vec<Variable>,
+    /// Whether every variable is assumed not to be 0, as is usual when simplifying by hand.
+    pub all_nonzero: bool,
+}
+
+impl Assumptions {
+    /// Whether the expression under `node_ref` is known not to be 0, e.g. a nonzero number, a
+    /// constant, a variable assumed to be nonzero, or a product or power of those.
+    pub fn is_nonzero(&self, node_ref: &TreeNodeRef<Token>) -> bool {
+        if let Ok(Value::Number(num)) = Tree::new(node_ref.clone()).eval(&Bindings::new()) {
+            return !num.is_zero();
+        }
+        let node = node_ref.borrow();
+        let nonzero = |child: &Option<TreeNodeRef<Token>>| {
+            child.as_ref().is_some_and(|child| self.is_nonzero(child))
+        };
+        match node.value {
+            Token::Number(num) | Token::Imaginary(num) => !num.is_zero(),
+            Token::Const(_) => true,
+            Token::Var(variable) => self.all_nonzero || self.nonzero.contains(&variable),
+            Token::Op(Operator::Multiply | Operator::Divide) => {
+                nonzero(&node.left) && nonzero(&node.right)
+            }
+            Token::Op(Operator::Exponent) => nonzero(&node.left),
+            Token::UnOp(UnaryOperator::Negation | UnaryOperator::SquareRoot) => {
+                nonzero(&node.right)
+            }
+            Token::UnOp(UnaryOperator::Exponential) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the expression under `node_ref` is known to have a value, e.g. a number, a
+    /// variable, or a sum, product or sine of those. Dividing needs the divisor to be nonzero,
+    /// a power needs a positive integer exponent or a nonzero base, and functions such as `ln`
+    /// or `sqrt` are only known to have a value when their argument is a number in their domain.
+    pub fn is_defined(&self, node_ref: &TreeNodeRef<Token>) -> bool {
+        match Tree::new(node_ref.clone()).eval(&Bindings::new()) {
+            Ok(Value::Number(_)) => return true,
+            Err(EvalError::UnboundVariables { .. }) | Ok(Value::Partial(_)) => {}
+            Err(_) => return false,
+        }
+        let node = node_ref.borrow();
+        let defined = |child: &Option<TreeNodeRef<Token>>| {
+            child.as_ref().is_none_or(|child| self.is_defined(child))
+        };
+        let operands = defined(&node.left) && defined(&node.right);
+        match node.value {
+            Token::Number(_)
+            | Token::Imaginary(_)
+            | Token::Const(_)
+            | Token::Var(_)
+            | Token::Meta(_) => true,
+            Token::Op(Operator::Add | Operator::Subtract | Operator::Multiply) | Token::Comma => {
+                operands
+            }
+            Token::Op(Operator::Divide) => {
+                operands
+                    && node
+                        .right
+                        .as_ref()
+                        .is_some_and(|right| self.is_nonzero(right))
+            }
+            Token::Op(Operator::Exponent) => {
+                let positive = node.right.as_ref().is_some_and(|right| {
+                    matches!(right.borrow().value, Token::Number(Num::Rational(exponent))
+                        if exponent.is_integer() && !exponent.is_negative() && !exponent.is_zero())
+                });
+                let nonzero = node.left.as_ref().is_some_and(|left| self.is_nonzero(left));
+                operands && (positive || nonzero)
+            }
+            Token::UnOp(
+                UnaryOperator::Negation
+                | UnaryOperator::Sine
+                | UnaryOperator::Cosine
+                | UnaryOperator::ArcTangent
+                | UnaryOperator::HyperbolicSine
+                | UnaryOperator::HyperbolicCosine
+                | UnaryOperator::HyperbolicTangent
+                | UnaryOperator::InverseHyperbolicSine
+                | UnaryOperator::Exponential,
+            )
+            | Token::Func(_) => operands,
+            _ => false,
+        }
+    }
+}
+
+impl Comparison {
+    pub fn holds(&self, left: Num, right: Num) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left.partial_cmp(&right).is_some_and(|order| order.is_ne()),
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    pub fn new(left: Tree, comparison: Comparison, right: Tree) -> Self {
+        Condition {
+            left,
+            comparison,
+            right,
+        }
+    }
+
+    /// Whether the condition holds with each metavariable standing for what it captured.
+    fn holds(
+        &self,
+        captures: &[(Variable, TreeNodeRef<Token>)],
+        assumptions: &Assumptions,
+    ) -> bool {
+        let left = substitute(&self.left.root, captures);
+        let right = substitute(&self.right.root, captures);
+        let value = |side: &TreeNodeRef<Token>| match Tree::new(side.clone()).eval(&Bindings::new())
+        {
+            Ok(Value::Number(num)) => Some(num),
+            _ => None,
+        };
+        match (self.comparison, value(&left), value(&right)) {
+            (comparison, Some(a), Some(b)) => comparison.holds(a, b),
+            (Comparison::NotEqual, None, Some(zero)) if zero.is_zero() => {
+                assumptions.is_nonzero(&left)
+            }
+            (Comparison::NotEqual, Some(zero), None) if zero.is_zero() => {
+                assumptions.is_nonzero(&right)
+            }
+            (Comparison::Equal, ..) => left == right,
+            _ => false,
+        }
+    }
+}
+
+impl Rule {
+    /// Returns `None` if the replacement has a metavariable which is not in the pattern, as it
+    /// would not stand for anything.
+    pub fn new(pattern: Tree, replacement: Tree) -> Option<Rule> {
+        let bound = pattern.metavariables();
+        if replacement
+            .metavariables()
+            .iter()
+            .any(|variable| !bound.contains(variable))
+        {
+            return None;
+        }
+        Some(Rule {
+            pattern: pattern.root,
+            replacement: replacement.root,
+            conditions: vec![],
+        })
+    }
+
+    /// Adds a condition which has to hold for the rule to be applied. Returns `None` if the
+    /// condition has a metavariable which is not in the pattern.
+    pub fn with_condition(mut self, condition: Condition) -> Option<Rule> {
+        let bound = self.pattern().metavariables();
+        if [&condition.left, &condition.right]
+            .iter()
+            .flat_map(|side| side.metavariables())
+            .any(|variable| !bound.contains(&variable))
+        {
+            return None;
+        }
+        self.conditions.push(condition);
+        Some(self)
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Reads a rule written as `pattern => replacement`, optionally followed by `if` and one or
+    /// more conditions joined by `and`, e.g. `?a / ?a => 1 if ?a != 0`. Each part is read by the
+    /// `Lexer`, with metavariables written as `?` followed by a name, e.g. `?a` or `?base`.
+    /// Conditions compare two expressions with `==`, `!=`, `<`, `<=`, `>` or `>=`.
+    /// Errors point at the offending part of `s`.
+    pub fn parse(s: &str) -> Result<Rule, ParseError> {
+        let malformed = |start: usize, end: usize| ParseError::MalformedRule {
+            span: Span::new(start, end),
+        };
+        let Some(arrow) = s.find("=>") else {
+            return Err(malformed(0, s.len()));
+        };
+        if let Some(second) = s[arrow + 2..].find("=>") {
+            let second = arrow + 2 + second;
+            return Err(malformed(second, second + 2));
+        }
+        let (end, conditions) = match find_word(s, arrow + 2, "if") {
+            Some(keyword) => (keyword, Some(keyword + 2)),
+            None => (s.len(), None),
+        };
+
+        let (pattern, _) = parse_part(s, 0, arrow)?;
+        let bound = pattern.metavariables();
+        let check_bound = |tokens: &[Spanned<Token>]| match tokens.iter().find(
+            |token| matches!(token.value, Token::Meta(variable) if !bound.contains(&variable)),
+        ) {
+            Some(token) => Err(ParseError::UnboundMetavariable { span: token.span }),
+            None => Ok(()),
+        };
+        let (replacement, tokens) = parse_part(s, arrow + 2, end)?;
+        check_bound(&tokens)?;
+        let mut rule = Rule::new(pattern, replacement).unwrap();
+
+        let Some(mut start) = conditions else {
+            return Ok(rule);
+        };
+        loop {
+            let end = find_word(s, start, "and").unwrap_or(s.len());
+            let (offset, symbol, comparison) = COMPARISONS
+                .iter()
+                .filter_map(|(symbol, comparison)| {
+                    let offset = s[start..end].find(symbol)?;
+                    Some((start + offset, *symbol, *comparison))
+                })
+                .min_by_key(|(offset, symbol, _)| (*offset, usize::MAX - symbol.len()))
+                .ok_or(malformed(start, end))?;
+            let (left, left_tokens) = parse_part(s, start, offset)?;
+            let (right, right_tokens) = parse_part(s, offset + symbol.len(), end)?;
+            check_bound(&left_tokens)?;
+            check_bound(&right_tokens)?;
+            rule = rule
+                .with_condition(Condition::new(left, comparison, right))
+                .unwrap();
+            if end == s.len() {
+                return Ok(rule);
+            }
+            start = end + 3;
+        }
+    }
+
+    /// Reads a list of rules, one per line, see `Rule::parse`. Blank lines and lines starting with
+    /// `#` are skipped. Errors point at the offending part of `s` as a whole.
+    pub fn parse_list(s: &str) -> Result<Vec<Rule>, ParseError> {
+        let mut rules = vec![];
+        let mut start = 0;
+        for line in s.split_inclusive('\n') {
+            let text = line.trim_end();
+            if !text.trim().is_empty() && !text.trim_start().starts_with('#') {
+                rules.push(Rule::parse(text).map_err(|error| error.offset(start))?);
+            }
+            start += line.len();
+        }
+        Ok(rules)
+    }
+
+    pub fn pattern(&self) -> Tree {
+        Tree::new(self.pattern.clone())
+    }
+
+    pub fn replacement(&self) -> Tree {
+        Tree::new(self.replacement.clone())
+    }
+}
+
+/// One step from a node to one of its children.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Child {
+    Left,
+    Right,
+}
+
+/// The path from the root of a tree to one of its nodes, e.g. `[Right, Left]` for the `b` in
+/// `a * (b + c)`. The root itself is the empty path.
+pub type Site = Vec<Child>;
+
 impl Tree {
     pub fn new(root: TreeNodeRef<Token>) -> Self {
-        Tree { root }
+        Tree {
+            root,
+            spans: vec![],
+        }
+    }
+
+    pub fn root(&self) -> &TreeNodeRef<Token> {
+        &self.root
+    }
+
+    /// The span of the input string `node` was built from, covering its whole sub-expression,
+    /// e.g. for `2 * (x + 1)` the `+` node spans `(x + 1)`.
+    /// Returns `None` if `node` is not part of this tree or the tree was not built from a string.
+    pub fn span_of(&self, node: &TreeNodeRef<Token>) -> Option<Span> {
+        self.spans
+            .iter()
+            .find(|(spanned_node, _)| Rc::ptr_eq(spanned_node, node))
+            .map(|(_, span)| *span)
+    }
+
+    /// Construct a new binary tree representation of the expression from an in-order vec of
+    /// spanned tokens, as produced by `Lexer`. Each node of the tree remembers the span of the
+    /// sub-expression it was built from, see `Tree::span_of`.
+    /// Malformed input, e.g. an operator missing an operand or an unclosed parenthesis, is
+    /// returned as a `ParseError` pointing at the offending token.
+    pub fn new_pre_from_in(in_order: Vec<Spanned<Token>>) -> Result<Tree, ParseError> {
+        let tokens: Vec<Token> = in_order.iter().map(|token| token.value).collect();
+        let span_at = |index: usize| {
+            in_order
+                .get(index)
+                .map(|token| token.span)
+                .unwrap_or_default()
+        };
+        match Tree::build(&tokens) {
+            Ok((root, ranges)) => Ok(Tree {
+                root,
+                spans: ranges
+                    .into_iter()
+                    .map(|(node, (first, last))| (node, span_at(first).join(span_at(last))))
+                    .collect(),
+            }),
+            Err(TreeError::UnbalancedParen { index }) => Err(ParseError::UnbalancedParen {
+                span: span_at(index),
+            }),
+            Err(TreeError::DanglingOperator { index }) => Err(ParseError::DanglingOperator {
+                span: span_at(index),
+            }),
+            Err(TreeError::MissingOperator { index }) => Err(ParseError::MissingOperator {
+                span: span_at(index),
+            }),
+            Err(TreeError::MisplacedComma { index }) => Err(ParseError::MisplacedComma {
+                span: span_at(index),
+            }),
+            Err(TreeError::WrongArgumentCount { index }) => Err(ParseError::WrongArgumentCount {
+                span: span_at(index),
+            }),
+            // Point at the whole of an empty pair of parentheses.
+            Err(TreeError::EmptyInput { index }) => Err(ParseError::EmptyInput {
+                span: span_at(index).join(span_at(index + 1)),
+            }),
+        }
     }
 
     /// Construct a new binary tree representation of the expression from an in-order vec of
-    /// tokens.
-    /// Assumes the vec is well-formed, including appropriate number of parentheses.
+    /// tokens, checking while building that every operator has its operands, that operands are
+    /// joined by operators, and that parentheses are balanced. The returned `TreeError` holds the
+    /// index of the token which broke the grammar, e.g. the `+` in `1 +`.
+    pub fn try_from_tokens(in_order: Vec<Token>) -> Result<Tree, TreeError> {
+        Tree::build(&in_order).map(|(root, _)| Tree::new(root))
+    }
+
+    /// Builds the tree, returning its root along with the range of tokens (first and last index)
+    /// each node was built from.
     /// Algorithm mostly cribbed from this C++ implementation, modified to include unary operators:
     ///     https://leetcode.ca/2020-04-14-1597-Build-Binary-Expression-Tree-From-Infix-Expression/
-    pub fn new_pre_from_in(in_order: Vec<Token>) -> Tree {
-        let mut ops: Vec<Token> = vec![];
-        let mut stack: Vec<TreeNodeRef<Token>> = vec![];
+    #[allow(clippy::type_complexity)]
+    fn build(
+        in_order: &[Token],
+    ) -> Result<(TreeNodeRef<Token>, Vec<(TreeNodeRef<Token>, TokenRange)>), TreeError> {
+        // Operators waiting for their operands, with their index in `in_order`.
+        let mut ops: Vec<(Token, usize)> = vec![];
+        let mut stack: Vec<(TreeNodeRef<Token>, TokenRange)> = vec![];
+        let mut ranges: Vec<(TreeNodeRef<Token>, TokenRange)> = vec![];
+        // Whether the next token must begin an operand, i.e. be a number, variable, constant,
+        // unary operator or opening parenthesis. Anything else there is missing an operand before
+        // it.
+        let mut expect_operand = true;
 
-        for token in in_order {
+        for (index, token) in in_order.iter().copied().enumerate() {
             match token {
-                Token::LParen => ops.push(token),
-
-                Token::Number(n) => {
-                    stack.push(TreeNode::new_rc(Token::Number(n), None, None));
-                    if matches!(ops.last(), Some(Token::UnOp(_))) {
-                        while matches!(ops.last(), Some(Token::UnOp(_))) {
-                            Tree::combine(&mut ops, &mut stack);
-                        }
+                Token::LParen => {
+                    if !expect_operand {
+                        return Err(TreeError::MissingOperator { index });
                     }
+                    ops.push((token, index));
                 }
-                Token::Var(v) => {
-                    stack.push(TreeNode::new_rc(Token::Var(v), None, None));
-                    if matches!(ops.last(), Some(Token::UnOp(_))) {
-                        while matches!(ops.last(), Some(Token::UnOp(_))) {
-                            Tree::combine(&mut ops, &mut stack);
-                        }
+
+                Token::Number(_)
+                | Token::Imaginary(_)
+                | Token::Var(_)
+                | Token::Meta(_)
+                | Token::Const(_) => {
+                    if !expect_operand {
+                        return Err(TreeError::MissingOperator { index });
                     }
+                    expect_operand = false;
+                    let leaf = TreeNode::new_rc(token, None, None);
+                    ranges.push((leaf.clone(), (index, index)));
+                    stack.push((leaf, (index, index)));
                 }
                 Token::RParen => {
-                    while let Some(stack_op) = ops.last() {
-                        if stack_op != &Token::LParen {
-                            Tree::combine(&mut ops, &mut stack);
-                        } else {
-                            ops.pop();
-                            break;
+                    let Some(lparen) = ops.iter().rposition(|(op, _)| *op == Token::LParen) else {
+                        return Err(TreeError::UnbalancedParen { index });
+                    };
+                    if expect_operand {
+                        return Err(match in_order[index - 1] {
+                            Token::LParen => TreeError::EmptyInput { index: index - 1 },
+                            _ => TreeError::DanglingOperator { index: index - 1 },
+                        });
+                    }
+                    while ops.len() > lparen + 1 {
+                        Tree::combine(&mut ops, &mut stack, &mut ranges)?;
+                    }
+                    let (_, lparen_index) = ops.pop().unwrap();
+                    // A parenthesized sub-expression includes its parentheses.
+                    if let Some((top, range)) = stack.last_mut() {
+                        *range = (lparen_index, index);
+                        if let Some(entry) = ranges
+                            .iter_mut()
+                            .rev()
+                            .find(|(node, _)| Rc::ptr_eq(node, top))
+                        {
+                            entry.1 = *range;
                         }
                     }
                 }
-                Token::Op(op) => {
-                    while !ops.is_empty() && ops.last().unwrap().priority() >= op.priority() {
-                        Tree::combine(&mut ops, &mut stack);
+                Token::Op(_) | Token::Comma => {
+                    if expect_operand {
+                        return Err(TreeError::DanglingOperator { index });
+                    }
+                    if token == Token::Comma && !Tree::in_arguments(&ops) {
+                        return Err(TreeError::MisplacedComma { index });
                     }
-                    ops.push(Token::Op(op));
+                    expect_operand = true;
+                    // Operators waiting on the stack which bind tighter take their operands first.
+                    let info = token.info().unwrap();
+                    while matches!(ops.last(), Some((top, _))
+                        if top.info().is_some_and(|top| top.binds_before(&info)))
+                    {
+                        Tree::combine(&mut ops, &mut stack, &mut ranges)?;
+                    }
+                    ops.push((token, index));
                 }
-                Token::UnOp(un_op) => {
-                    ops.push(Token::UnOp(un_op));
+                Token::UnOp(_) | Token::Func(_) => {
+                    if !expect_operand {
+                        return Err(TreeError::MissingOperator { index });
+                    }
+                    ops.push((token, index));
                 }
             }
         }
 
-        while stack.len() > 1 || !ops.is_empty() {
-            Tree::combine(&mut ops, &mut stack);
+        if in_order.is_empty() {
+            return Err(TreeError::EmptyInput { index: 0 });
+        }
+        if expect_operand {
+            return Err(TreeError::DanglingOperator {
+                index: in_order.len() - 1,
+            });
+        }
+        while let Some((top, index)) = ops.last() {
+            if *top == Token::LParen {
+                return Err(TreeError::UnbalancedParen { index: *index });
+            }
+            Tree::combine(&mut ops, &mut stack, &mut ranges)?;
+        }
+
+        match stack.pop() {
+            Some((root, _)) => Ok((root, ranges)),
+            None => Err(TreeError::EmptyInput { index: 0 }),
         }
+    }
 
-        Tree {
-            root: stack.pop().unwrap(),
+    /// Evaluates the tree in the number type `N`, e.g. `f64`, or `Complex` to allow results like
+    /// `sqrt(-1)` which are not real numbers. Returns `None` if the tree has variables, or has an
+    /// operation without a value in `N`, e.g. `1 / 0`.
+    pub fn evaluate<N: Numeric>(&self) -> Option<N> {
+        self.evaluate_with(&Bindings::new()).ok()
+    }
+
+    /// Same as `Tree::evaluate`, with each variable taking its value from `bindings`. Variables
+    /// without a value are all listed in `EvalError::UnboundVariables`. An operation without a
+    /// value, e.g. `1 / 0`, or `sqrt(-1)` in the real numbers, is an `EvalError` whose index is
+    /// the position of its token in `create_vec(Ordering::Post)`, the same as for `Expr`.
+    pub fn evaluate_with<N: Numeric>(&self, bindings: &Bindings<N>) -> Result<N, EvalError> {
+        bindings.check(&self.variables())?;
+        evaluate_node(&self.root, bindings, &mut 0)
+    }
+
+    /// Evaluates the tree with each variable taking its value from `bindings`. Numbers are kept
+    /// exact where possible, see `Num`, and so are the exact values of functions at constants,
+    /// e.g. `sin(pi)` is 0, while other constants are turned into floats, e.g. `2 * pi`.
+    /// When some variables have no value, every sub-expression without them is replaced by its
+    /// value instead, returning the reduced tree, e.g. `2 * a + x / 4` with `a = 3` becomes
+    /// `6 + x / 4`. Constants are kept in the reduced tree, with the sub-expressions of them
+    /// simplified, e.g. `x * pi / 2 + sin(pi)` becomes `x * pi / 2 + 0`.
+    /// An operation without a value, e.g. `1 / 0`, is an `EvalError` whose index is the position
+    /// of its token in `create_vec(Ordering::Post)`, the same as for `Expr`. So is an imaginary
+    /// number when every variable has a value, as it cannot be a `Value::Number`.
+    pub fn eval(&self, bindings: &Bindings) -> Result<Value, EvalError> {
+        let closed = bindings.check(&self.variables()).is_ok() && self.metavariables().is_empty();
+        let (root, value) = reduce_node(&self.root, bindings, closed, &mut 0)?;
+        match value {
+            Some(num) => match simplified(&root).borrow().value {
+                Token::Number(exact) => Ok(Value::Number(exact)),
+                _ => Ok(Value::Number(num)),
+            },
+            None => Ok(Value::Partial(Tree::new(root))),
         }
     }
 
-    pub fn save_typst_tree(&self, file: &'static str) -> std::io::Result<()> {
-        let bin_tree_struct: binary_tree_ds::Tree<Token> =
-            binary_tree_ds::Tree::new(self.root.clone());
-        bin_tree_struct.save_typst(file)?;
-        Ok(())
+    /// The sub-expression at `site`, or `None` if the tree has no node there.
+    pub fn node_at(&self, site: &[Child]) -> Option<TreeNodeRef<Token>> {
+        let mut node = self.root.clone();
+        for child in site {
+            let next = match child {
+                Child::Left => node.borrow().left.clone()?,
+                Child::Right => node.borrow().right.clone()?,
+            };
+            node = next;
+        }
+        Some(node)
+    }
+
+    /// The sites of every sub-expression matching the pattern of `rule`, outermost first and then
+    /// from left to right.
+    pub fn find(&self, rule: &Rule) -> Vec<Site> {
+        self.find_assuming(rule, &Assumptions::default())
+    }
+
+    /// Same as `Tree::find`, with the conditions of `rule` checked under `assumptions`.
+    pub fn find_assuming(&self, rule: &Rule, assumptions: &Assumptions) -> Vec<Site> {
+        let mut sites = vec![];
+        find_sites(&self.root, rule, assumptions, &mut vec![], &mut sites);
+        sites
+    }
+
+    /// Rewrites the sub-expression at `site` with `rule`, returning whether its pattern matched
+    /// there. The rest of the tree is left as it was.
+    /// The rewritten tree is made of new nodes, so any clone of the tree is left unchanged, and
+    /// the spans of the input string are dropped, see `Tree::span_of`.
+    pub fn apply_at(&mut self, rule: &Rule, site: &[Child]) -> bool {
+        self.apply_at_assuming(rule, site, &Assumptions::default())
+    }
+
+    /// Same as `Tree::apply_at`, with the conditions of `rule` checked under `assumptions`.
+    pub fn apply_at_assuming(
+        &mut self,
+        rule: &Rule,
+        site: &[Child],
+        assumptions: &Assumptions,
+    ) -> bool {
+        let Some(node) = self.node_at(site) else {
+            return false;
+        };
+        let Some(captures) = match_rule(rule, &node, assumptions) else {
+            return false;
+        };
+        let replacement = substitute(&rule.replacement, &captures);
+        self.root = replace_at(&self.root, site, replacement);
+        self.spans.clear();
+        true
+    }
+
+    /// Rewrites the tree with `rules` until none of them match anywhere, returning the number of
+    /// rewrites made. Each step applies the first rule which matches, at its outermost match.
+    /// Rules can undo each other or grow the tree forever, e.g. `?a + ?b => ?b + ?a`, so at most
+    /// `limit` rewrites are made.
+    pub fn rewrite(&mut self, rules: &[Rule], limit: usize) -> usize {
+        self.rewrite_assuming(rules, limit, &Assumptions::default())
+    }
+
+    /// Same as `Tree::rewrite`, with the conditions of the rules checked under `assumptions`.
+    pub fn rewrite_assuming(
+        &mut self,
+        rules: &[Rule],
+        limit: usize,
+        assumptions: &Assumptions,
+    ) -> usize {
+        for step in 0..limit {
+            let next = rules.iter().find_map(|rule| {
+                let site = self.find_assuming(rule, assumptions).into_iter().next();
+                site.map(|site| (rule, site))
+            });
+            match next {
+                Some((rule, site)) => {
+                    self.apply_at_assuming(rule, &site, assumptions);
+                }
+                None => return step,
+            }
+        }
+        limit
+    }
+
+    /// The metavariables in the tree, each listed once in the order they first appear in the
+    /// expression.
+    pub fn metavariables(&self) -> Vec<Variable> {
+        self.unique_variables(|token| match token {
+            Token::Meta(variable) => Some(variable),
+            _ => None,
+        })
+    }
+
+    /// The variables in the tree, each listed once in the order they first appear in the
+    /// expression.
+    pub fn variables(&self) -> Vec<Variable> {
+        self.unique_variables(|token| match token {
+            Token::Var(variable) => Some(variable),
+            _ => None,
+        })
+    }
+
+    // The variables picked out of the tokens by `pick`, each listed once in the order they first
+    // appear in the expression.
+    fn unique_variables(&self, pick: impl Fn(Token) -> Option<Variable>) -> Vec<Variable> {
+        let mut variables = vec![];
+        for variable in self.create_vec(Ordering::In).into_iter().filter_map(pick) {
+            if !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+        variables
+    }
+
+    /// Saves a drawing of the tree to `file` as a Typst document, using the cetz package.
+    /// Functions are drawn with one child per argument rather than the commas joining them.
+    pub fn save_typst_tree(&self, file: &str) -> std::io::Result<()> {
+        std::fs::write(file, TYPST_TREE.replace("{data}", &typst_data(&self.root)))
     }
 
     pub fn create_vec(&self, order: Ordering) -> Vec<Token> {
@@ -90,17 +727,427 @@ impl Tree {
         stack
     }
 
-    fn combine(ops: &mut Vec<Token>, stack: &mut Vec<TreeNodeRef<Token>>) {
-        let mut root = TreeNode::new(ops.pop().unwrap(), None, None);
-        if matches!(root.value, Token::UnOp(_)) {
-            root.right = Some(stack.pop().unwrap());
-            println!("unary right: {:?}", root.right);
+    /// The arguments of a function, given the right child of its `Token::Func` node, e.g. `a`,
+    /// `b` and `c` for `max(a, b, c)`. The arguments are joined by `Token::Comma` nodes, so a
+    /// function node is an n-ary node within the binary tree.
+    pub fn arguments(node: &TreeNodeRef<Token>) -> Vec<TreeNodeRef<Token>> {
+        let borrowed = node.borrow();
+        match (&borrowed.value, &borrowed.left, &borrowed.right) {
+            (Token::Comma, Some(left), Some(right)) => {
+                let mut arguments = Tree::arguments(left);
+                arguments.extend(Tree::arguments(right));
+                arguments
+            }
+            _ => vec![node.clone()],
+        }
+    }
+
+    /// Whether the innermost open parenthesis on `ops` directly follows a function, so that its
+    /// contents are the arguments of that function, e.g. `log(2, x)`.
+    fn in_arguments(ops: &[(Token, usize)]) -> bool {
+        match ops.iter().rposition(|(op, _)| *op == Token::LParen) {
+            Some(lparen) if lparen > 0 => {
+                let (function, function_index) = ops[lparen - 1];
+                matches!(function, Token::UnOp(_) | Token::Func(_))
+                    && function_index + 1 == ops[lparen].1
+            }
+            _ => false,
+        }
+    }
+
+    /// Pops the top operator and combines it with its operand(s) from the top of the stack into a
+    /// new node, which is pushed back onto the stack.
+    /// A `Token::Func` keeps its comma separated list of arguments as its right child, while a
+    /// unary operator given two arguments becomes the binary operator taking them as operands,
+    /// e.g. `log(2, x)` becomes `Op(Logarithm)` with operands 2, x.
+    /// The range of tokens the new node covers is recorded in `ranges`.
+    fn combine(
+        ops: &mut Vec<(Token, usize)>,
+        stack: &mut Vec<(TreeNodeRef<Token>, TokenRange)>,
+        ranges: &mut Vec<(TreeNodeRef<Token>, TokenRange)>,
+    ) -> Result<(), TreeError> {
+        let Some((op, index)) = ops.pop() else {
+            return Ok(());
+        };
+        let dangling = TreeError::DanglingOperator { index };
+        let mut root = TreeNode::new(op, None, None);
+        let (right, (_, last)) = stack.pop().ok_or(dangling)?;
+        let mut first = index;
+        if let Token::Func(function) = op {
+            if !function.accepts(Tree::arguments(&right).len()) {
+                return Err(TreeError::WrongArgumentCount { index });
+            }
+            root.right = Some(right);
+        } else if root.value.info().is_some_and(|info| info.arity == 2) {
+            let (left, (left_first, _)) = stack.pop().ok_or(dangling)?;
+            first = left_first;
+            root.left = Some(left);
+            root.right = Some(right);
+        } else if right.borrow().value == Token::Comma {
+            let arguments = right.borrow();
+            root = match op {
+                Token::UnOp(UnaryOperator::CommonLogarithm)
+                    if arguments.left.as_ref().unwrap().borrow().value != Token::Comma =>
+                {
+                    TreeNode::new(
+                        Token::Op(Operator::Logarithm),
+                        arguments.left.clone(),
+                        arguments.right.clone(),
+                    )
+                }
+                _ => return Err(TreeError::WrongArgumentCount { index }),
+            };
         } else {
-            root.right = Some(stack.pop().unwrap());
-            root.left = Some(stack.pop().unwrap());
+            root.right = Some(right);
+        }
+        let node = Rc::new(RefCell::new(root));
+        ranges.push((node.clone(), (first, last)));
+        stack.push((node, (first, last)));
+        Ok(())
+    }
+}
+
+/// Recursive function used in method `evaluate_with`. `index` counts the nodes in post-order, to
+/// report where an error happened.
+fn evaluate_node<N: Numeric>(
+    node_ref: &TreeNodeRef<Token>,
+    bindings: &Bindings<N>,
+    index: &mut usize,
+) -> Result<N, EvalError> {
+    let node = node_ref.borrow();
+    let mut operands = vec![];
+    for child in node.left.iter().chain(&node.right) {
+        evaluate_operands(child, bindings, index, &mut operands)?;
+    }
+    let position = *index;
+    *index += 1;
+    let result = match (node.value, operands.as_slice()) {
+        (Token::Number(num), _) => N::from_num(num),
+        (Token::Const(constant), _) => N::from_constant(constant),
+        (Token::Var(variable), _) => bindings.get(variable).unwrap(),
+        // Types without imaginary numbers have no value for them.
+        (Token::Imaginary(num), _) => match N::from_imaginary(num) {
+            value if value.is_nan() => return Err(EvalError::Domain { index: position }),
+            value => value,
+        },
+        // A metavariable stands for an expression, which never has a value.
+        (Token::Meta(variable), _) => {
+            return Err(EvalError::UnboundVariables {
+                variables: vec![variable],
+            })
+        }
+        (Token::Op(op), [left, right]) => op.apply_num(*left, *right),
+        (Token::UnOp(un_op), [x]) => un_op.apply_num(*x),
+        (Token::Func(function), arguments) => {
+            let arguments: Vec<f64> = arguments.iter().map(|argument| argument.to_f64()).collect();
+            N::from_f64(function.apply(&arguments))
+        }
+        _ => return Err(EvalError::StackUnderflow { index: position }),
+    };
+    if matches!(node.value, Token::Op(_) | Token::UnOp(_) | Token::Func(_)) {
+        EvalError::check(node.value, &operands, result, position)?;
+    }
+    Ok(result)
+}
+
+/// Evaluates the operand `node_ref` into `operands`, or each of the arguments when it is a comma
+/// separated list of them, counting the commas in `index` too.
+fn evaluate_operands<N: Numeric>(
+    node_ref: &TreeNodeRef<Token>,
+    bindings: &Bindings<N>,
+    index: &mut usize,
+    operands: &mut Vec<N>,
+) -> Result<(), EvalError> {
+    let node = node_ref.borrow();
+    if node.value != Token::Comma {
+        operands.push(evaluate_node(node_ref, bindings, index)?);
+        return Ok(());
+    }
+    for child in node.left.iter().chain(&node.right) {
+        evaluate_operands(child, bindings, index, operands)?;
+    }
+    *index += 1;
+    Ok(())
+}
+
+/// Recursive function used in method `eval`, returning a new node for the reduced expression,
+/// and its value when it has no variables without a value. `closed` is whether the whole tree has
+/// none. `index` counts the nodes in post-order, to report where an error happened.
+fn reduce_node(
+    node_ref: &TreeNodeRef<Token>,
+    bindings: &Bindings,
+    closed: bool,
+    index: &mut usize,
+) -> Result<(TreeNodeRef<Token>, Option<Num>), EvalError> {
+    let node = node_ref.borrow();
+    let mut operands = vec![];
+    let mut reduce = |child: &Option<TreeNodeRef<Token>>, index: &mut usize| {
+        child
+            .as_ref()
+            .map(|child| reduce_operands(child, bindings, closed, index, &mut operands))
+            .transpose()
+    };
+    let left = reduce(&node.left, index)?;
+    let right = reduce(&node.right, index)?;
+    let position = *index;
+    *index += 1;
+
+    let leaf = |num: Num| Ok((TreeNode::new_rc(Token::Number(num), None, None), Some(num)));
+    let values: Option<Vec<Num>> = operands.iter().map(|(_, value)| *value).collect();
+    let value = match (node.value, values.as_deref()) {
+        (Token::Number(num), _) => return leaf(num),
+        (Token::Var(variable), _) => match bindings.get(variable) {
+            Some(value) => return leaf(Num::from(value)),
+            None => None,
+        },
+        (Token::Const(constant), _) => Some(Num::from_constant(constant)),
+        (Token::Imaginary(_), _) if closed => return Err(EvalError::Domain { index: position }),
+        (Token::Op(op), Some([left, right])) => Some(op.apply_num(*left, *right)),
+        (Token::UnOp(un_op), Some([x])) => Some(un_op.apply_num(*x)),
+        (Token::Func(function), Some(arguments)) => {
+            let floats: Vec<f64> = arguments.iter().map(Num::to_f64).collect();
+            Some(Num::from_f64(function.apply(&floats)))
+        }
+        _ => None,
+    };
+    if let (Some(result), Some(values)) = (value, &values) {
+        EvalError::check(node.value, values, result, position)?;
+    }
+    let numbers = operands.iter().all(|(operand, _)| {
+        let operand = operand.borrow();
+        matches!(operand.value, Token::Number(_)) && operand.is_leaf()
+    });
+    match value {
+        Some(result) if numbers && !operands.is_empty() => return leaf(result),
+        Some(_) => {}
+        // The operands with a value are as reduced as they get, so their constants are simplified.
+        None => {
+            for (operand, value) in &operands {
+                if value.is_some() && !operand.borrow().is_leaf() {
+                    operand.swap(&simplified(operand));
+                }
+            }
         }
-        stack.push(Rc::new(RefCell::new(root)));
     }
+    Ok((TreeNode::new_rc(node.value, left, right), value))
+}
+
+/// Reduces the operand `node_ref` into `operands`, or each of the arguments when it is a comma
+/// separated list of them, counting the commas in `index` too. Returns the new node for it.
+fn reduce_operands(
+    node_ref: &TreeNodeRef<Token>,
+    bindings: &Bindings,
+    closed: bool,
+    index: &mut usize,
+    operands: &mut Vec<(TreeNodeRef<Token>, Option<Num>)>,
+) -> Result<TreeNodeRef<Token>, EvalError> {
+    let node = node_ref.borrow();
+    if node.value != Token::Comma {
+        let (operand, value) = reduce_node(node_ref, bindings, closed, index)?;
+        operands.push((operand.clone(), value));
+        return Ok(operand);
+    }
+    let mut reduce = |child: &Option<TreeNodeRef<Token>>, index: &mut usize| {
+        child
+            .as_ref()
+            .map(|child| reduce_operands(child, bindings, closed, index, operands))
+            .transpose()
+    };
+    let left = reduce(&node.left, index)?;
+    let right = reduce(&node.right, index)?;
+    *index += 1;
+    Ok(TreeNode::new_rc(Token::Comma, left, right))
+}
+
+/// The expression under `node_ref`, which has no variables, simplified with its exact values,
+/// e.g. `sin(pi) => 0`.
+fn simplified(node_ref: &TreeNodeRef<Token>) -> TreeNodeRef<Token> {
+    Tree::new(node_ref.clone()).simplify().root().clone()
+}
+
+// The index of the first `word` in `s` after `start` which is a whole word, e.g. "if" but not
+// the "if" in "?diff".
+fn find_word(s: &str, start: usize, word: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '?';
+    s[start..]
+        .match_indices(word)
+        .map(|(i, _)| start + i)
+        .find(|&i| !s[..i].ends_with(is_word) && !s[i + word.len()..].starts_with(is_word))
+}
+
+// Reads `s[start..end]`, a part of a rule, into a tree, also returning its tokens. Spans are
+// those of the whole of `s`.
+fn parse_part(
+    s: &str,
+    start: usize,
+    end: usize,
+) -> Result<(Tree, Vec<Spanned<Token>>), ParseError> {
+    let lexer = Lexer::new_inorder(&s[start..end]).map_err(|error| error.offset(start))?;
+    let tokens: Vec<Spanned<Token>> = lexer
+        .list
+        .iter()
+        .map(|token| {
+            let span = Span::new(token.span.start + start, token.span.end + start);
+            Spanned::new(token.value, span)
+        })
+        .collect();
+    Ok((Tree::new_pre_from_in(tokens.clone())?, tokens))
+}
+
+/// Whether `node_ref` matches the pattern of `rule` and its conditions hold, returning the
+/// sub-expression each metavariable matched.
+fn match_rule(
+    rule: &Rule,
+    node_ref: &TreeNodeRef<Token>,
+    assumptions: &Assumptions,
+) -> Option<Vec<(Variable, TreeNodeRef<Token>)>> {
+    let mut captures = vec![];
+    let kept = Tree::new(rule.replacement.clone()).metavariables();
+    let matches = match_node(&rule.pattern, node_ref, &mut captures)
+        && rule
+            .conditions
+            .iter()
+            .all(|condition| condition.holds(&captures, assumptions))
+        && captures
+            .iter()
+            .filter(|(variable, _)| !kept.contains(variable))
+            .all(|(_, capture)| assumptions.is_defined(capture));
+    matches.then_some(captures)
+}
+
+/// Recursive function used in method `find`, pushing the site of each node under `node_ref`
+/// which matches `rule`, where `site` is the path to `node_ref`.
+fn find_sites(
+    node_ref: &TreeNodeRef<Token>,
+    rule: &Rule,
+    assumptions: &Assumptions,
+    site: &mut Site,
+    sites: &mut Vec<Site>,
+) {
+    if match_rule(rule, node_ref, assumptions).is_some() {
+        sites.push(site.clone());
+    }
+    let node = node_ref.borrow();
+    for (child, next) in [(Child::Left, &node.left), (Child::Right, &node.right)] {
+        if let Some(next) = next {
+            site.push(child);
+            find_sites(next, rule, assumptions, site, sites);
+            site.pop();
+        }
+    }
+}
+
+/// Whether `node_ref` matches `pattern`, adding the sub-expression each metavariable matched to
+/// `captures`.
+fn match_node(
+    pattern: &TreeNodeRef<Token>,
+    node_ref: &TreeNodeRef<Token>,
+    captures: &mut Vec<(Variable, TreeNodeRef<Token>)>,
+) -> bool {
+    let pattern = pattern.borrow();
+    if let Token::Meta(variable) = pattern.value {
+        return match captures.iter().find(|(captured, _)| *captured == variable) {
+            Some((_, expression)) => expression == node_ref,
+            None => {
+                captures.push((variable, node_ref.clone()));
+                true
+            }
+        };
+    }
+    let node = node_ref.borrow();
+    let children_match = |pattern: &Option<TreeNodeRef<Token>>,
+                          node: &Option<TreeNodeRef<Token>>,
+                          captures: &mut Vec<_>| match (pattern, node) {
+        (Some(pattern), Some(node)) => match_node(pattern, node, captures),
+        (None, None) => true,
+        _ => false,
+    };
+    pattern.value == node.value
+        && children_match(&pattern.left, &node.left, captures)
+        && children_match(&pattern.right, &node.right, captures)
+}
+
+/// A copy of `replacement` with each metavariable replaced by the sub-expression it captured.
+fn substitute(
+    replacement: &TreeNodeRef<Token>,
+    captures: &[(Variable, TreeNodeRef<Token>)],
+) -> TreeNodeRef<Token> {
+    let node = replacement.borrow();
+    if let Token::Meta(variable) = node.value {
+        if let Some((_, expression)) = captures.iter().find(|(captured, _)| *captured == variable) {
+            return expression.clone();
+        }
+    }
+    let child = |child: &Option<TreeNodeRef<Token>>| {
+        child.as_ref().map(|child| substitute(child, captures))
+    };
+    TreeNode::new_rc(node.value, child(&node.left), child(&node.right))
+}
+
+/// A copy of the tree under `node_ref` with the node at `site` replaced by `replacement`. Only the
+/// nodes on the path to `site` are copied, the rest are shared.
+fn replace_at(
+    node_ref: &TreeNodeRef<Token>,
+    site: &[Child],
+    replacement: TreeNodeRef<Token>,
+) -> TreeNodeRef<Token> {
+    let Some((first, rest)) = site.split_first() else {
+        return replacement;
+    };
+    let node = node_ref.borrow();
+    let (mut left, mut right) = (node.left.clone(), node.right.clone());
+    let next = match first {
+        Child::Left => &mut left,
+        Child::Right => &mut right,
+    };
+    *next = next
+        .as_ref()
+        .map(|next| replace_at(next, rest, replacement));
+    TreeNode::new_rc(node.value, left, right)
+}
+
+const TYPST_TREE: &str = r#"
+#let data = (
+{data}
+)
+
+#import "@preview/cetz:0.1.2": canvas, draw, tree
+
+#canvas(length: 1cm, {
+  import draw: *
+
+  set-style(content: (padding: .2),
+    fill: gray.lighten(70%),
+    stroke: gray.lighten(70%))
+
+  tree.tree(data, spread: 2.5, grow: 1.5, draw-node: (node, _) => {
+    circle((), radius: .45, stroke: none)
+    content((), node.content)
+  }, draw-edge: (from, to, _) => {
+    line((a: from, number: .6, abs: true, b: to),
+         (a: to, number: .6, abs: true, b: from), mark: (end: ">"))
+  }, name: "tree")
+})
+"#;
+
+/// Recursive function used in method `save_typst_tree` to write a node and its children as a
+/// nested Typst array, e.g. `([+], [1.000], [x])`.
+fn typst_data(node_ref: &TreeNodeRef<Token>) -> String {
+    let node = node_ref.borrow();
+    if node.is_leaf() {
+        // An underscore would start emphasis in Typst markup, rather than showing a subscript.
+        return format!("[{}]", node.value.to_string().replace('_', "\\_"));
+    }
+    let children = match (node.value, &node.right) {
+        (Token::Func(_), Some(arguments)) => Tree::arguments(arguments),
+        _ => node.left.iter().chain(node.right.iter()).cloned().collect(),
+    };
+    let mut data = format!("([{}]", node.value);
+    for child in children.iter() {
+        data += ", ";
+        data += &typst_data(child);
+    }
+    data + ")"
 }
 
 /// Recursive function used in method `create_vec` to take the tree and return a vector of the tree
@@ -129,6 +1176,15 @@ impl Tree {
 ///         2   4
 /// becomes
 /// 3 + 2 + 4, not 3 + (2 + 4)
+///
+/// The tree
+///         -
+///        / \
+///       3   -
+///          / \
+///         2   4
+/// becomes
+/// 3 - (2 - 4), as 3 - 2 - 4 would be a different expression
 fn push_into_order(node_ref: &TreeNodeRef<Token>, stack: &mut Vec<Token>, order: Ordering) {
     // Early return to avoid adding extraneous parentheses
     if node_ref.borrow().is_leaf() {
@@ -136,38 +1192,69 @@ fn push_into_order(node_ref: &TreeNodeRef<Token>, stack: &mut Vec<Token>, order:
         return;
     }
 
-    if order == Ordering::Pre {
-        stack.push(node_ref.borrow().value);
+    let node = node_ref.borrow();
+    // The logarithm in an arbitrary base is written as a function, log(b, x).
+    if order == Ordering::In && node.value == Token::Op(Operator::Logarithm) {
+        stack.push(Token::UnOp(UnaryOperator::CommonLogarithm));
+        stack.push(Token::LParen);
+        if let Some(ref base) = node.left {
+            push_into_order(base, stack, order);
+        }
+        stack.push(Token::Comma);
+        if let Some(ref argument) = node.right {
+            push_into_order(argument, stack, order);
+        }
+        stack.push(Token::RParen);
+        return;
     }
-    if let Some(ref left) = node_ref.borrow().left {
-        if !matches!(left.borrow().value, Token::Number(_))
-            && !matches!(left.borrow().value, Token::Var(_))
-            && node_ref.borrow().value.priority() > left.borrow().value.priority()
-        {
+    // Arguments of a function are always parenthesized, max(a, b, c).
+    if order == Ordering::In {
+        if let (Token::Func(_), Some(arguments)) = (node.value, &node.right) {
+            stack.push(node.value);
             stack.push(Token::LParen);
-            push_into_order(left, stack, order);
+            push_into_order(arguments, stack, order);
             stack.push(Token::RParen);
-        } else {
-            push_into_order(left, stack, order);
+            return;
         }
     }
+    if order == Ordering::Pre {
+        stack.push(node.value);
+    }
+    if let Some(ref left) = node.left {
+        push_operand(node.value, left, true, stack, order);
+    }
     if order == Ordering::In {
-        stack.push(node_ref.borrow().value);
+        stack.push(node.value);
     }
-    if let Some(ref right) = node_ref.borrow().right {
-        if !matches!(right.borrow().value, Token::Number(_))
-            && !matches!(right.borrow().value, Token::Var(_))
-            && node_ref.borrow().value.priority() > right.borrow().value.priority()
-        {
-            stack.push(Token::LParen);
-            push_into_order(right, stack, order);
-            stack.push(Token::RParen);
-        } else {
-            push_into_order(right, stack, order);
-        }
+    if let Some(ref right) = node.right {
+        push_operand(node.value, right, false, stack, order);
     }
     if order == Ordering::Post {
-        stack.push(node_ref.borrow().value);
+        stack.push(node.value);
+    }
+}
+
+/// Pushes the left (`is_left`) or right operand `child` of the operator `parent`, surrounded by
+/// parentheses when the operator table says they are needed to keep the meaning of the tree.
+/// Parentheses are only needed, and only added, for `Ordering::In`.
+fn push_operand(
+    parent: Token,
+    child: &TreeNodeRef<Token>,
+    is_left: bool,
+    stack: &mut Vec<Token>,
+    order: Ordering,
+) {
+    let needs_parens = order == Ordering::In
+        && match (parent.info(), child.borrow().value.info()) {
+            (Some(parent), Some(child)) => parent.needs_parens(&child, is_left),
+            _ => false,
+        };
+    if needs_parens {
+        stack.push(Token::LParen);
+        push_into_order(child, stack, order);
+        stack.push(Token::RParen);
+    } else {
+        push_into_order(child, stack, order);
     }
 }
 
@@ -0,0 +1,107 @@
+#[cfg(test)]
+#[test]
+fn bindings_evaluate() {
+    use parse_eq::bindings::Bindings;
+    use parse_eq::expr::Expr;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Complex;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder("x^2 + 2xy - y").unwrap().list).unwrap();
+    assert_eq!(vec![Variable::X, Variable::Y], tree.variables());
+    let expr = Expr::from(&tree);
+
+    // The same bindings are reused for every set of values.
+    let mut bindings = Bindings::new();
+    for (x, y) in [(1.0, 2.0), (-3.0, 0.5), (0.0, 0.0)] {
+        bindings.insert(Variable::X, x);
+        bindings.insert(Variable::Y, y);
+        let value = x * x + 2.0 * x * y - y;
+        assert_eq!(Ok(value), expr.eval_with(&bindings));
+        assert_eq!(Ok(value), tree.evaluate_with(&bindings));
+    }
+
+    let bindings = Bindings::new().with(Variable::X, Complex::I);
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder("x^2").unwrap().list).unwrap();
+    assert_eq!(Ok(-1.0), tree.evaluate_with(&bindings).map(|z| z.re));
+    assert_eq!(None, tree.evaluate::<Complex>());
+}
+
+#[test]
+fn bindings_errors() {
+    use parse_eq::bindings::Bindings;
+    use parse_eq::error::EvalError;
+    use parse_eq::expr::Expr;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Complex;
+    use parse_eq::num::Numeric;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    // The tree reports the same error as `Expr`, at the same post-order index.
+    let bindings = Bindings::new().with(Variable::X, 0.0);
+    for (input, error) in [
+        ("1 / x", EvalError::DivisionByZero { index: 2 }),
+        ("2 + x ^ -1", EvalError::DivisionByZero { index: 4 }),
+        ("sqrt(x - 1)", EvalError::Domain { index: 3 }),
+        ("max(1, ln x) * 2", EvalError::Domain { index: 2 }),
+        ("hypot(1, 2) + 3 + 4i", EvalError::Domain { index: 6 }),
+        ("log(1 + x, 8)", EvalError::Domain { index: 4 }),
+        ("log(x - 2, 8)", EvalError::Domain { index: 4 }),
+        ("2 ^ (10 ^ 9) + x", EvalError::Overflow { index: 4 }),
+        ("exp(1000 + x)", EvalError::Overflow { index: 3 }),
+    ] {
+        let tree = Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+        assert_eq!(Err(error.clone()), Expr::from(&tree).eval_with(&bindings));
+        assert_eq!(Err(error), tree.evaluate_with(&bindings), "{}", input);
+    }
+
+    // Over the complex numbers only dividing by zero is left without a value.
+    let bindings = Bindings::new().with(Variable::X, Complex::from_f64(0.0));
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder("sqrt(x - 1)").unwrap().list).unwrap();
+    assert_eq!(Ok(Complex::I), tree.evaluate_with(&bindings));
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder("1 / x").unwrap().list).unwrap();
+    assert_eq!(
+        Err(EvalError::DivisionByZero { index: 2 }),
+        tree.evaluate_with(&bindings)
+    );
+}
+
+#[test]
+fn bindings_unbound() {
+    use parse_eq::bindings::Bindings;
+    use parse_eq::error::EvalError;
+    use parse_eq::expr::Expr;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    let a = Variable::new("a");
+    let theta = Variable::new("θ");
+    let input = "a sin(theta) + x * a - y";
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    let bindings: Bindings = [(Variable::X, 1.0)].into_iter().collect();
+
+    // Every variable without a value is listed, once each.
+    let error = EvalError::UnboundVariables {
+        variables: vec![a, theta, Variable::Y],
+    };
+    assert_eq!(Err(error.clone()), Expr::from(&tree).eval_with(&bindings));
+    assert_eq!(Err(error.clone()), tree.evaluate_with(&bindings));
+    assert_eq!("no value for a, θ, y", error.to_string());
+
+    let mut bindings = bindings
+        .with(a, 2.0)
+        .with(theta, 0.0)
+        .with(Variable::Y, 3.0);
+    assert_eq!(Ok(-1.0), tree.evaluate_with(&bindings));
+    assert_eq!(Some(3.0), bindings.remove(Variable::Y));
+    assert_eq!(None, bindings.get(Variable::Y));
+    assert_eq!(
+        Err(EvalError::UnboundVariables {
+            variables: vec![Variable::Y]
+        }),
+        Expr::from(&tree).eval_with(&bindings)
+    );
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+#[test]
+fn diagnostic_unknown_word() {
+    use parse_eq::lexer::Identifiers;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+
+    // Reading whole words, an unknown word called as a function is likely a typo.
+    let options = LexerOptions {
+        identifiers: Identifiers::Words,
+        ..Default::default()
+    };
+    let input = "2 * sinq(x)";
+    let error = Lexer::new_inorder_with(input, options).err().unwrap();
+    assert_eq!(
+        "error: unknown symbol
+  |
+1 | 2 * sinq(x)
+  |     ^^^^
+  = hint: did you mean `sin`?
+",
+        error.diagnostic(input).render(input)
+    );
+}
+
+#[test]
+fn diagnostic_unclosed_paren() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::tree::Tree;
+
+    let input = "2 * (1 + 3";
+    let lexer = Lexer::new_inorder(input).unwrap();
+    let error = Tree::new_pre_from_in(lexer.list).unwrap_err();
+    assert_eq!(
+        "error: unbalanced parenthesis
+  |
+1 | 2 * (1 + 3
+  |     ^
+  = hint: missing `)` for the `(` opened here
+",
+        error.diagnostic(input).render(input)
+    );
+}
+
+#[test]
+fn diagnostic_dangling_operator() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::tree::Tree;
+
+    let input = "1 +";
+    let lexer = Lexer::new_inorder(input).unwrap();
+    let error = Tree::new_pre_from_in(lexer.list).unwrap_err();
+    assert_eq!(
+        "error: operator is missing an operand
+  |
+1 | 1 +
+  |   ^
+  = hint: `+` needs an operand on each side
+",
+        error.diagnostic(input).render(input)
+    );
+}
+
+#[test]
+fn diagnostic_unicode_columns() {
+    use parse_eq::lexer::Lexer;
+
+    // The carets are placed by character, not by byte.
+    let input = "(€) + ¥";
+    let error = Lexer::new_inorder(input).err().unwrap();
+    assert_eq!(
+        "error: unknown symbol
+  |
+1 | (€) + ¥
+  |  ^
+",
+        error.diagnostic(input).render(input)
+    );
+}
+
+#[test]
+fn diagnostic_multiline() {
+    use parse_eq::diagnostic::Diagnostic;
+    use parse_eq::token::Span;
+
+    let input = "x + 1\n+ y $ 2";
+    let diagnostic = Diagnostic::new("unknown symbol", Span::new(10, 11));
+    assert_eq!(
+        "error: unknown symbol
+  |
+2 | + y $ 2
+  |     ^
+",
+        diagnostic.render(input)
+    );
+}
+
+#[test]
+fn diagnostic_span_off_char_boundary() {
+    use parse_eq::diagnostic::Diagnostic;
+    use parse_eq::token::Span;
+
+    // A span inside `α`, which is two bytes long, or past the end of the input is widened to the
+    // characters it touches rather than panicking.
+    let input = "α+";
+    for (span, carets) in [
+        (Span::new(1, 2), "^"),
+        (Span::new(1, 1), "^"),
+        (Span::new(1, 10), "^^"),
+        (Span::new(5, 9), "  ^"),
+    ] {
+        let diagnostic = Diagnostic::new("unknown symbol", span);
+        assert_eq!(
+            format!(
+                "error: unknown symbol
+  |
+1 | α+
+  | {}
+",
+                carets
+            ),
+            diagnostic.render(input)
+        );
+    }
+}
+
+#[test]
+fn diagnostic_ansi() {
+    use parse_eq::diagnostic::Diagnostic;
+    use parse_eq::token::Span;
+
+    let input = "1 2";
+    let diagnostic = Diagnostic::new("missing operator between operands", Span::new(2, 3))
+        .with_hint("add an operator, e.g. `*`, before this");
+    assert_eq!(
+        "\x1b[1;31merror\x1b[0m: missing operator between operands
+  |
+1 | 1 2
+  |   \x1b[1;31m^\x1b[0m
+  = \x1b[1;36mhint\x1b[0m: add an operator, e.g. `*`, before this
+",
+        diagnostic.render_ansi(input)
+    );
+}
+
+#[test]
+fn diagnostic_rule() {
+    use parse_eq::tree::Rule;
+
+    let input = "?a + 0 => ?a\n?a * ?b => ?c";
+    let error = Rule::parse_list(input).unwrap_err();
+    assert_eq!(
+        "error: metavariable is not in the pattern
+  |
+2 | ?a * ?b => ?c
+  |            ^^
+  = hint: `?c` has to appear in the pattern, before the `=>`
+",
+        error.diagnostic(input).render(input)
+    );
+}
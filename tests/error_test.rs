@@ -0,0 +1,499 @@
+#[cfg(test)]
+#[test]
+fn lexer_unknown_symbol() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Identifiers;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+    use parse_eq::token::Span;
+
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(2, 3)
+        },
+        Lexer::new_inorder("2 $ 3").err().unwrap()
+    );
+    let options = LexerOptions {
+        identifiers: Identifiers::Words,
+        ..Default::default()
+    };
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(4, 8)
+        },
+        Lexer::new_inorder_with("1 + sinq(x)", options)
+            .err()
+            .unwrap()
+    );
+    // A subscript must follow a variable, and cannot be empty.
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(3, 4)
+        },
+        Lexer::new_inorder("sin_2 x").err().unwrap()
+    );
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(1, 2)
+        },
+        Lexer::new_inorder("x_ + 1").err().unwrap()
+    );
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(3, 4)
+        },
+        Lexer::new_inorder("a_b_ + 1").err().unwrap()
+    );
+}
+
+#[test]
+fn lexer_unknown_symbol_unicode() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+
+    // Spans are byte offsets, so the multi-byte '€' covers three bytes.
+    assert_eq!(
+        ParseError::UnknownSymbol {
+            span: Span::new(4, 7)
+        },
+        Lexer::new_inorder("1 + €").err().unwrap()
+    );
+}
+
+#[test]
+fn lexer_malformed_number() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+
+    assert_eq!(
+        ParseError::MalformedNumber {
+            span: Span::new(4, 9)
+        },
+        Lexer::new_inorder("x + 1.2.3").err().unwrap()
+    );
+    for (input, span) in [
+        ("1__000", Span::new(0, 6)),
+        ("1_000_", Span::new(0, 6)),
+        ("1_.5", Span::new(0, 4)),
+        ("0x1G + 1", Span::new(0, 4)),
+        ("0b102", Span::new(0, 5)),
+        ("0x", Span::new(0, 2)),
+        ("2 * 1e5.5", Span::new(4, 9)),
+    ] {
+        assert_eq!(
+            ParseError::MalformedNumber { span },
+            Lexer::new_inorder(input).err().unwrap(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn lexer_empty_input() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+
+    assert_eq!(
+        ParseError::EmptyInput {
+            span: Span::new(0, 0)
+        },
+        Lexer::new_inorder("").err().unwrap()
+    );
+    assert_eq!(
+        ParseError::EmptyInput {
+            span: Span::new(0, 3)
+        },
+        Lexer::new_inorder("   ").err().unwrap()
+    );
+}
+
+#[test]
+fn lexer_wrong_ordering() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+
+    let mut lexer = Lexer::new_inorder("1 + 2*3").unwrap();
+    lexer.in_to_pre().unwrap();
+    let pre_order = lexer.list.clone();
+    assert_eq!(
+        ParseError::WrongOrdering {
+            span: Span::new(0, 7)
+        },
+        lexer.in_to_pre().err().unwrap()
+    );
+    assert_eq!(pre_order, lexer.list);
+}
+
+#[test]
+fn lexer_spans() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+
+    let lexer = Lexer::new_inorder("12 + sin x").unwrap();
+    assert_eq!(
+        vec![
+            Span::new(0, 2),
+            Span::new(3, 4),
+            Span::new(5, 8),
+            Span::new(9, 10)
+        ],
+        lexer
+            .list
+            .iter()
+            .map(|token| token.span)
+            .collect::<Vec<Span>>()
+    );
+}
+
+#[test]
+fn tree_unbalanced_paren() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let lexer = Lexer::new_inorder("2 * (1 + 3").unwrap();
+    assert_eq!(
+        ParseError::UnbalancedParen {
+            span: Span::new(4, 5)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+
+    let lexer = Lexer::new_inorder(")(").unwrap();
+    assert_eq!(
+        ParseError::UnbalancedParen {
+            span: Span::new(0, 1)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+}
+
+#[test]
+fn tree_dangling_operator() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let lexer = Lexer::new_inorder("1 +").unwrap();
+    assert_eq!(
+        ParseError::DanglingOperator {
+            span: Span::new(2, 3)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+
+    let lexer = Lexer::new_inorder("* 3").unwrap();
+    assert_eq!(
+        ParseError::DanglingOperator {
+            span: Span::new(0, 1)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+
+    let lexer = Lexer::new_inorder("sin").unwrap();
+    assert_eq!(
+        ParseError::DanglingOperator {
+            span: Span::new(0, 3)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+}
+
+#[test]
+fn tree_missing_operator() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let lexer = Lexer::new_inorder("1 2").unwrap();
+    assert_eq!(
+        ParseError::MissingOperator {
+            span: Span::new(2, 3)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+    // A number is only multiplied implicitly when it comes first, so `2x` is a product but a
+    // number after a variable or a `)` is missing its operator.
+    for (input, span) in [
+        ("x2", Span::new(1, 2)),
+        ("x2y", Span::new(1, 2)),
+        ("(a+b)2", Span::new(5, 6)),
+    ] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        assert_eq!(
+            ParseError::MissingOperator { span },
+            Tree::new_pre_from_in(lexer.list).unwrap_err(),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn tree_empty_parens() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let lexer = Lexer::new_inorder("2 * ( )").unwrap();
+    assert_eq!(
+        ParseError::EmptyInput {
+            span: Span::new(4, 7)
+        },
+        Tree::new_pre_from_in(lexer.list).unwrap_err()
+    );
+}
+
+#[test]
+fn tree_try_from_tokens() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::tree::Tree;
+
+    let tokens = vec![
+        Number(Num::from(1.0)),
+        Op(Add),
+        Number(Num::from(2.0)),
+        Op(Multiply),
+        Number(Num::from(3.0)),
+    ];
+    let lexer = Lexer::new_inorder("1 + 2 * 3").unwrap();
+    assert_eq!(
+        Tree::new_pre_from_in(lexer.list).unwrap(),
+        Tree::try_from_tokens(tokens).unwrap()
+    );
+}
+
+#[test]
+fn tree_try_from_tokens_errors() {
+    use parse_eq::error::TreeError;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    // 1 +
+    assert_eq!(
+        TreeError::DanglingOperator { index: 1 },
+        Tree::try_from_tokens(vec![Number(Num::from(1.0)), Op(Add)]).unwrap_err()
+    );
+    // ) (
+    assert_eq!(
+        TreeError::UnbalancedParen { index: 0 },
+        Tree::try_from_tokens(vec![RParen, LParen]).unwrap_err()
+    );
+    // * 3
+    assert_eq!(
+        TreeError::DanglingOperator { index: 0 },
+        Tree::try_from_tokens(vec![Op(Multiply), Number(Num::from(3.0))]).unwrap_err()
+    );
+    // 1 + * 2
+    assert_eq!(
+        TreeError::DanglingOperator { index: 2 },
+        Tree::try_from_tokens(vec![
+            Number(Num::from(1.0)),
+            Op(Add),
+            Op(Multiply),
+            Number(Num::from(2.0))
+        ])
+        .unwrap_err()
+    );
+    // ( 1 + )
+    assert_eq!(
+        TreeError::DanglingOperator { index: 2 },
+        Tree::try_from_tokens(vec![LParen, Number(Num::from(1.0)), Op(Add), RParen]).unwrap_err()
+    );
+    // 1 2 +
+    assert_eq!(
+        TreeError::MissingOperator { index: 1 },
+        Tree::try_from_tokens(vec![
+            Number(Num::from(1.0)),
+            Number(Num::from(2.0)),
+            Op(Add)
+        ])
+        .unwrap_err()
+    );
+    // 2 sin x
+    assert_eq!(
+        TreeError::MissingOperator { index: 1 },
+        Tree::try_from_tokens(vec![Number(Num::from(2.0)), UnOp(Sine), Var(Variable::X)])
+            .unwrap_err()
+    );
+    // ( 2 * ( 1 + 3 )
+    assert_eq!(
+        TreeError::UnbalancedParen { index: 0 },
+        Tree::try_from_tokens(vec![
+            LParen,
+            Number(Num::from(2.0)),
+            Op(Multiply),
+            LParen,
+            Number(Num::from(1.0)),
+            Op(Add),
+            Number(Num::from(3.0)),
+            RParen
+        ])
+        .unwrap_err()
+    );
+    // ( )
+    assert_eq!(
+        TreeError::EmptyInput { index: 0 },
+        Tree::try_from_tokens(vec![LParen, RParen]).unwrap_err()
+    );
+    assert_eq!(
+        TreeError::EmptyInput { index: 0 },
+        Tree::try_from_tokens(vec![]).unwrap_err()
+    );
+}
+
+#[test]
+fn tree_function_arguments() {
+    use parse_eq::error::ParseError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    for (input, error) in [
+        (
+            "sin(1, 2)",
+            ParseError::WrongArgumentCount {
+                span: Span::new(0, 3),
+            },
+        ),
+        (
+            "log(1, 2, 3)",
+            ParseError::WrongArgumentCount {
+                span: Span::new(0, 3),
+            },
+        ),
+        (
+            "(1, 2) + 3",
+            ParseError::MisplacedComma {
+                span: Span::new(2, 3),
+            },
+        ),
+        (
+            "1, 2",
+            ParseError::MisplacedComma {
+                span: Span::new(1, 2),
+            },
+        ),
+        (
+            "log((1, 2))",
+            ParseError::MisplacedComma {
+                span: Span::new(6, 7),
+            },
+        ),
+        (
+            "log(, 2)",
+            ParseError::DanglingOperator {
+                span: Span::new(4, 5),
+            },
+        ),
+        (
+            "hypot(1)",
+            ParseError::WrongArgumentCount {
+                span: Span::new(0, 5),
+            },
+        ),
+        (
+            "clamp(x, 1)",
+            ParseError::WrongArgumentCount {
+                span: Span::new(0, 5),
+            },
+        ),
+        (
+            "max(1, 2) 3",
+            ParseError::MissingOperator {
+                span: Span::new(10, 11),
+            },
+        ),
+        (
+            "max(1, 2) (3, 4)",
+            ParseError::MisplacedComma {
+                span: Span::new(12, 13),
+            },
+        ),
+    ] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        assert_eq!(Err(error), Tree::new_pre_from_in(lexer.list), "{}", input);
+    }
+}
+
+#[test]
+fn rule_parse_errors() {
+    use parse_eq::error::ParseError;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Rule;
+
+    for (input, error) in [
+        (
+            "?a + 0 -> ?a",
+            ParseError::MalformedRule {
+                span: Span::new(0, 12),
+            },
+        ),
+        (
+            "?a => ?a => ?a",
+            ParseError::MalformedRule {
+                span: Span::new(9, 11),
+            },
+        ),
+        (
+            "?a / ?a => 1 if ?a",
+            ParseError::MalformedRule {
+                span: Span::new(15, 18),
+            },
+        ),
+        (
+            "?a + ?b => ?c",
+            ParseError::UnboundMetavariable {
+                span: Span::new(11, 13),
+            },
+        ),
+        (
+            "?a / ?b => ?a if ?c != 0",
+            ParseError::UnboundMetavariable {
+                span: Span::new(17, 19),
+            },
+        ),
+        (
+            "?a + => ?a",
+            ParseError::DanglingOperator {
+                span: Span::new(3, 4),
+            },
+        ),
+        (
+            "? + 1 => 1",
+            ParseError::UnknownSymbol {
+                span: Span::new(0, 1),
+            },
+        ),
+        (
+            " => 1",
+            ParseError::EmptyInput {
+                span: Span::new(0, 1),
+            },
+        ),
+    ] {
+        assert_eq!(Err(error), Rule::parse(input), "{}", input);
+    }
+    assert_eq!(
+        Err(ParseError::MalformedNumber {
+            span: Span::new(16, 21),
+        }),
+        Rule::parse_list("?a + 0 => ?a\n?a 1.2.3 => ?a")
+    );
+}
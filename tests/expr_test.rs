@@ -2,23 +2,114 @@
 #[test]
 fn simple() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
-    let add_pre = vec![Op(Add), Number(1.0), Number(2.0)];
+    let add_pre = vec![Op(Add), Number(Num::from(1.0)), Number(Num::from(2.0))];
 
     let mut lexer = Lexer::new_inorder("1+2").unwrap();
-    lexer.in_to_pre();
+    lexer.in_to_pre().unwrap();
     assert_eq!(add_pre, lexer.list);
 }
 
 #[test]
 fn multiply_and_add() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
-    let add_mult_pre = vec![Op(Add), Number(1.0), Op(Multiply), Number(2.0), Number(3.0)];
+    let add_mult_pre = vec![
+        Op(Add),
+        Number(Num::from(1.0)),
+        Op(Multiply),
+        Number(Num::from(2.0)),
+        Number(Num::from(3.0)),
+    ];
 
     let mut lexer = Lexer::new_inorder("1+2*3").unwrap();
-    lexer.in_to_pre();
+    lexer.in_to_pre().unwrap();
     assert_eq!(add_mult_pre, lexer.list);
 }
+
+#[test]
+fn eval_post_order() {
+    use parse_eq::expr::Expr;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::tree::Tree;
+
+    for (input, value) in [
+        ("1 + 2 * 3", 7.0),
+        ("(1 + 2) * 3", 9.0),
+        ("2 ^ 3 ^ 2", 512.0),
+        ("10 - 4 - 3", 3.0),
+        ("-2 ^ 2", -4.0),
+        ("8 / 4 / 2", 1.0),
+        ("sqrt(16) + ln(e)", 5.0),
+        ("log_2 8", 3.0),
+        ("log(3, 81)", 4.0),
+        ("max(1, 5, 3) - min(4, 2)", 3.0),
+        ("clamp(7, 0, hypot(3, 4))", 5.0),
+        ("cos(pi)", -1.0),
+    ] {
+        let tree = Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+        assert_eq!(Ok(value), Expr::from(&tree).eval(), "{}", input);
+    }
+}
+
+#[test]
+fn eval_errors() {
+    use parse_eq::error::EvalError;
+    use parse_eq::expr::Expr;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Function::*;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    let eval = |input| {
+        let tree = Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+        Expr::from(&tree).eval()
+    };
+    assert_eq!(
+        Err(EvalError::DivisionByZero { index: 4 }),
+        eval("1 / (2 - 2)")
+    );
+    assert_eq!(Err(EvalError::DivisionByZero { index: 3 }), eval("0 ^ -1"));
+    assert_eq!(Err(EvalError::Domain { index: 2 }), eval("sqrt(-1)"));
+    assert_eq!(Err(EvalError::Domain { index: 1 }), eval("ln 0"));
+    assert_eq!(Err(EvalError::Domain { index: 1 }), eval("1 + 2i"));
+    assert_eq!(Err(EvalError::Domain { index: 2 }), eval("log(1, 2)"));
+    assert_eq!(Err(EvalError::Domain { index: 2 }), eval("log(0, 2)"));
+    assert_eq!(Err(EvalError::Domain { index: 3 }), eval("log(-2, 8)"));
+    assert_eq!(Err(EvalError::Overflow { index: 4 }), eval("2 ^ (10 ^ 9)"));
+    assert_eq!(
+        Err(EvalError::UnboundVariables {
+            variables: vec![Variable::Y, Variable::X]
+        }),
+        eval("y * x + y")
+    );
+
+    let one = || Number(Num::from(1));
+    assert_eq!(
+        Err(EvalError::StackUnderflow { index: 1 }),
+        Expr::from(vec![one(), Op(Add)]).eval()
+    );
+    assert_eq!(
+        Err(EvalError::StackUnderflow { index: 0 }),
+        Expr::from(vec![]).eval()
+    );
+    assert_eq!(
+        Err(EvalError::ExtraOperands { index: 1 }),
+        Expr::from(vec![one(), one()]).eval()
+    );
+    assert_eq!(
+        Err(EvalError::WrongArgumentCount { index: 2 }),
+        Expr::from(vec![one(), one(), Func(Hypotenuse)]).eval()
+    );
+    assert_eq!(
+        Err(EvalError::WrongArgumentCount { index: 3 }),
+        Expr::from(vec![one(), one(), Comma, Op(Add)]).eval()
+    );
+}
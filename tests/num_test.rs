@@ -0,0 +1,189 @@
+#[cfg(test)]
+#[test]
+fn rational_normalize() {
+    use num_bigint::BigInt;
+    use parse_eq::num::Rational;
+
+    let half = Rational::new(2, 4).unwrap();
+    assert_eq!(
+        (BigInt::from(1), BigInt::from(2)),
+        (half.numer(), half.denom())
+    );
+    let negative = Rational::new(3, -6).unwrap();
+    assert_eq!(
+        (BigInt::from(-1), BigInt::from(2)),
+        (negative.numer(), negative.denom())
+    );
+    assert_eq!(Rational::new(0, 5), Rational::new(0, -1));
+    assert_eq!(None, Rational::new(1, 0));
+    assert_eq!("-0.5", negative.to_string());
+    assert_eq!("3", Rational::new(6, 2).unwrap().to_string());
+}
+
+#[test]
+fn num_exact_arithmetic() {
+    use parse_eq::num::Num;
+    use parse_eq::num::Rational;
+
+    let third = Num::from(1) / Num::from(3);
+    let sixth = Num::from(1) / Num::from(6);
+    assert_eq!(Num::Rational(Rational::new(1, 2).unwrap()), third + sixth);
+    assert_eq!(Num::from(0.3), Num::from(0.1) + Num::from(0.2));
+    assert_eq!(Num::from(-1) / Num::from(6), sixth - third);
+    assert_eq!(Num::from(1) / Num::from(18), third * sixth);
+    assert_eq!("1/3", third.to_string());
+
+    // Integer powers stay exact, anything else is a float.
+    assert_eq!(
+        Num::from(9) / Num::from(4),
+        (Num::from(2) / Num::from(3)).pow(Num::from(-2))
+    );
+    assert!(matches!(Num::from(2).pow(Num::from(0.5)), Num::Float(_)));
+    // So does a power too large to work out.
+    assert!(matches!(
+        Num::from(3).pow(Num::from(100_000_000)),
+        Num::Float(_)
+    ));
+    // Dividing by zero behaves like floats.
+    assert_eq!(f64::INFINITY, (Num::from(1) / Num::from(0)).to_f64());
+}
+
+#[test]
+fn num_parse_decimal() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::num::Rational;
+    use parse_eq::token::Token::*;
+
+    assert_eq!(
+        Some(Num::Rational(Rational::new(1, 10).unwrap())),
+        Num::parse_decimal("0.1")
+    );
+    assert_eq!(
+        Some(Num::Rational(Rational::new(1, 2).unwrap())),
+        Num::parse_decimal(".5")
+    );
+    assert_eq!(
+        Some(Num::from(25) / Num::from(2)),
+        Num::parse_decimal("12.50")
+    );
+    assert_eq!(Some(Num::from(1500)), Num::parse_decimal("1.5e3"));
+    assert_eq!(
+        Some(Num::Rational(Rational::new(3, 1000).unwrap())),
+        Num::parse_decimal("3E-3")
+    );
+    assert_eq!(Some(Num::from(31)), Num::parse_integer("1F", 16));
+    assert_eq!(None, Num::parse_integer("12", 2));
+    assert_eq!(None, Num::parse_decimal("1e"));
+    assert_eq!(None, Num::parse_decimal("1.2.3"));
+    assert_eq!(None, Num::parse_decimal("."));
+    assert_eq!(
+        vec![Number(Num::Rational(Rational::new(1, 10).unwrap()))],
+        Lexer::new_inorder("0.1").unwrap().list
+    );
+}
+
+#[test]
+fn operator_apply_num() {
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::UnaryOperator::*;
+
+    let third = Num::from(1) / Num::from(3);
+    assert_eq!(Num::from(2) / Num::from(3), Add.apply_num(third, third));
+    assert_eq!(
+        Num::from(1) / Num::from(27),
+        Exponent.apply_num(third, Num::from(3))
+    );
+    assert_eq!(Num::from(-1) / Num::from(3), Negation.apply_num(third));
+    assert!(matches!(Sine.apply_num(third), Num::Float(_)));
+}
+
+#[test]
+fn num_display() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+
+    // Exact unless asked for a precision.
+    assert_eq!("1/3", (Num::from(1) / Num::from(3)).to_string());
+    assert_eq!("0.333", format!("{:.3}", Num::from(1) / Num::from(3)));
+    assert_eq!("0.125", (Num::from(1) / Num::from(8)).to_string());
+    assert_eq!("-0.04", (Num::from(-1) / Num::from(25)).to_string());
+    assert_eq!("12.5", Num::from(12.5).to_string());
+    assert_eq!("0.1", Num::Float(0.1).to_string());
+
+    let printed: Vec<String> = Lexer::new_inorder("0.100 * 2 + 3.25")
+        .unwrap()
+        .list
+        .iter()
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(vec!["0.1", "\\*", "2", "+", "3.25"], printed);
+    let printed: Vec<String> = Lexer::new_inorder("2 + 1.5")
+        .unwrap()
+        .list
+        .iter()
+        .map(|token| format!("{:.2}", token))
+        .collect();
+    assert_eq!(vec!["2.00", "+", "1.50"], printed);
+}
+
+#[test]
+fn numeric_backends() {
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::UnaryOperator::*;
+
+    // The same operators work in any backend, with their own precision.
+    assert_eq!(0.1_f32 + 0.2_f32, Add.apply_num(0.1_f32, 0.2_f32));
+    assert_eq!(0.1 + 0.2, Add.apply_num(0.1_f64, 0.2_f64));
+    assert_eq!(
+        Num::from(0.3),
+        Add.apply_num(Num::from(0.1), Num::from(0.2))
+    );
+    assert_eq!(1.0, Sine.apply_num(std::f64::consts::FRAC_PI_2));
+    assert_eq!(8.0_f32, Exponent.apply_num(2.0_f32, 3.0_f32));
+}
+
+#[test]
+fn num_large_fractions() {
+    use num_bigint::BigInt;
+    use parse_eq::num::Num;
+    use parse_eq::num::Rational;
+
+    // Fractions too large for an i128 stay exact.
+    let large = Num::from(10).pow(Num::from(40));
+    assert!(matches!(large, Num::Rational(_)));
+    assert_eq!(
+        "10000000000000000000000000000000000000000",
+        large.to_string()
+    );
+    assert_eq!(Num::from(1), large / large);
+    assert_eq!(Num::from(1), large - (large - Num::from(1)));
+    assert!(large + Num::from(1) > large);
+
+    let third_power = (Num::from(1) / Num::from(3)).pow(Num::from(100));
+    let Num::Rational(rational) = third_power else {
+        panic!("(1/3)^100 is not exact");
+    };
+    assert_eq!(BigInt::from(3).pow(100), rational.denom());
+    assert_eq!(Num::from(1), third_power * Num::from(3).pow(Num::from(100)));
+    assert!(third_power > Num::from(0));
+    assert!(!third_power.is_zero());
+
+    // 40! overflows an i128, but dividing it back down to 1 is exact.
+    let factorial = (1..=40).fold(Num::from(1), |product, n| product * Num::from(n));
+    assert_eq!(
+        Num::from(1),
+        (1..=40).fold(factorial, |quotient, n| quotient / Num::from(n))
+    );
+    // Equal fractions are equal however they were worked out.
+    assert_eq!(
+        Rational::new(i128::MAX, 1).unwrap(),
+        Rational::from_big(BigInt::from(i128::MAX).into())
+    );
+    assert_eq!(
+        Num::from(2).pow(Num::from(200)),
+        Num::from(4).pow(Num::from(100))
+    );
+}
@@ -24,29 +24,30 @@ fn split_nums_vars() {
 #[test]
 fn lexer_whitespace() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Token;
     assert_eq!(
-        vec![Token::Number(1.0)],
+        vec![Token::Number(Num::from(1.0))],
         Lexer::new_inorder("1").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(1.0)],
+        vec![Token::Number(Num::from(1.0))],
         Lexer::new_inorder(" 1").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(1.0)],
+        vec![Token::Number(Num::from(1.0))],
         Lexer::new_inorder("1 ").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(12.0)],
+        vec![Token::Number(Num::from(12.0))],
         Lexer::new_inorder("12").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(12.0)],
+        vec![Token::Number(Num::from(12.0))],
         Lexer::new_inorder(" 12").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(12.0)],
+        vec![Token::Number(Num::from(12.0))],
         Lexer::new_inorder("12 ").unwrap().list
     );
 }
@@ -54,17 +55,18 @@ fn lexer_whitespace() {
 #[test]
 fn lexer_decimal() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Token;
     assert_eq!(
-        vec![Token::Number(1.0)],
+        vec![Token::Number(Num::from(1.0))],
         Lexer::new_inorder("1.0").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(1.1)],
+        vec![Token::Number(Num::from(1.1))],
         Lexer::new_inorder("1.1").unwrap().list
     );
     assert_eq!(
-        vec![Token::Number(0.1)],
+        vec![Token::Number(Num::from(0.1))],
         Lexer::new_inorder("0.1").unwrap().list
     );
 }
@@ -158,23 +160,42 @@ fn lexer_variable_parens() {
 #[test]
 fn lexer_variable_operator() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::Variable;
     assert_eq!(
-        vec![Var(Variable::X), Op(Add), Number(1.0)],
+        vec![Var(Variable::X), Op(Add), Number(Num::from(1.0))],
         Lexer::new_inorder("x+1").unwrap().list
     );
     assert_eq!(
-        vec![LParen, Var(Variable::X), RParen, Op(Add), Number(1.0)],
+        vec![
+            LParen,
+            Var(Variable::X),
+            RParen,
+            Op(Add),
+            Number(Num::from(1.0))
+        ],
         Lexer::new_inorder("(x)+1").unwrap().list
     );
     assert_eq!(
-        vec![Var(Variable::X), Op(Add), LParen, Number(1.0), RParen],
+        vec![
+            Var(Variable::X),
+            Op(Add),
+            LParen,
+            Number(Num::from(1.0)),
+            RParen
+        ],
         Lexer::new_inorder("x+(1)").unwrap().list
     );
     assert_eq!(
-        vec![LParen, Var(Variable::X), Op(Add), Number(1.0), RParen],
+        vec![
+            LParen,
+            Var(Variable::X),
+            Op(Add),
+            Number(Num::from(1.0)),
+            RParen
+        ],
         Lexer::new_inorder("(x+1)").unwrap().list
     );
 }
@@ -182,13 +203,14 @@ fn lexer_variable_operator() {
 #[test]
 fn lexer_operator() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
 
-    let add = vec![Number(1.0), Op(Add), Number(2.0)];
-    let subtract = vec![Number(1.0), Op(Subtract), Number(2.0)];
-    let multiply = vec![Number(1.0), Op(Multiply), Number(2.0)];
-    let divide = vec![Number(1.0), Op(Divide), Number(2.0)];
+    let add = vec![Number(Num::from(1.0)), Op(Add), Number(Num::from(2.0))];
+    let subtract = vec![Number(Num::from(1.0)), Op(Subtract), Number(Num::from(2.0))];
+    let multiply = vec![Number(Num::from(1.0)), Op(Multiply), Number(Num::from(2.0))];
+    let divide = vec![Number(Num::from(1.0)), Op(Divide), Number(Num::from(2.0))];
 
     assert_eq!(add, Lexer::new_inorder("1+2").unwrap().list);
     assert_eq!(subtract, Lexer::new_inorder("1-2").unwrap().list);
@@ -199,13 +221,38 @@ fn lexer_operator() {
 #[test]
 fn lexer_operator_parentheses() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
 
-    let add = vec![LParen, Number(1.0), Op(Add), Number(2.0), RParen];
-    let subtract = vec![LParen, Number(1.0), Op(Subtract), Number(2.0), RParen];
-    let multiply = vec![LParen, Number(1.0), Op(Multiply), Number(2.0), RParen];
-    let divide = vec![LParen, Number(1.0), Op(Divide), Number(2.0), RParen];
+    let add = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Add),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
+    let subtract = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Subtract),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
+    let multiply = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Multiply),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
+    let divide = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Divide),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
 
     assert_eq!(add, Lexer::new_inorder("(1+2)").unwrap().list);
     assert_eq!(subtract, Lexer::new_inorder("(1-2)").unwrap().list);
@@ -216,10 +263,17 @@ fn lexer_operator_parentheses() {
 #[test]
 fn lexer_operator_parentheses_whitespace() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
 
-    let add = vec![LParen, Number(1.0), Op(Add), Number(2.0), RParen];
+    let add = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Add),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
 
     assert_eq!(add, Lexer::new_inorder("(1+2)").unwrap().list);
     assert_eq!(add, Lexer::new_inorder(" (1+2)").unwrap().list);
@@ -261,29 +315,35 @@ fn lexer_unop_trig() {
 #[test]
 fn lexer_unop_negation() {
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::token::Variable;
 
-    let neg_one = vec![UnOp(Negation), Number(1.0)];
+    let neg_one = vec![UnOp(Negation), Number(Num::from(1.0))];
     assert_eq!(neg_one, Lexer::new_inorder("-1").unwrap().list);
 
-    let double_neg = vec![UnOp(Negation), Number(1.0), Op(Add), Number(2.0)];
+    let double_neg = vec![
+        UnOp(Negation),
+        Number(Num::from(1.0)),
+        Op(Add),
+        Number(Num::from(2.0)),
+    ];
     assert_eq!(double_neg, Lexer::new_inorder("-1 + 2").unwrap().list);
 
     let trip_neg = vec![
         UnOp(Negation),
-        Number(1.0),
+        Number(Num::from(1.0)),
         Op(Add),
         UnOp(Negation),
-        Number(2.0),
+        Number(Num::from(2.0)),
     ];
     assert_eq!(trip_neg, Lexer::new_inorder("-1 + -2").unwrap().list);
 
     let trip_neg_var = vec![
         UnOp(Negation),
-        Number(1.0),
+        Number(Num::from(1.0)),
         Op(Add),
         UnOp(Negation),
         Var(Variable::X),
@@ -292,10 +352,10 @@ fn lexer_unop_negation() {
 
     let check_vec = vec![
         UnOp(Negation),
-        Number(2.0),
+        Number(Num::from(2.0)),
         Op(Subtract),
         LParen,
-        Number(5.0),
+        Number(Num::from(5.0)),
         Op(Multiply),
         UnOp(Negation),
         UnOp(Sine),
@@ -303,8 +363,724 @@ fn lexer_unop_negation() {
         Var(Variable::X),
         RParen,
         Op(Add),
-        Number(1.0),
+        Number(Num::from(1.0)),
     ];
     let lexer = Lexer::new_inorder("-2 - (5 * -sin -x ) + 1").unwrap();
     assert_eq!(check_vec, lexer.list);
 }
+
+#[test]
+fn lexer_exponent() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    let power = vec![Number(Num::from(2.0)), Op(Exponent), Number(Num::from(3.0))];
+    assert_eq!(power, Lexer::new_inorder("2^3").unwrap().list);
+    assert_eq!(power, Lexer::new_inorder("2**3").unwrap().list);
+    assert_eq!(power, Lexer::new_inorder("2 ** 3").unwrap().list);
+
+    let neg_power = vec![
+        Var(Variable::X),
+        Op(Exponent),
+        UnOp(Negation),
+        Number(Num::from(2.0)),
+    ];
+    assert_eq!(neg_power, Lexer::new_inorder("x^-2").unwrap().list);
+}
+
+#[test]
+fn operator_table() {
+    use parse_eq::num::Num;
+    use parse_eq::token::Associativity;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+
+    let add = Add.info();
+    let multiply = Multiply.info();
+    let exponent = Exponent.info();
+    let negation = Negation.info();
+
+    assert!(multiply.precedence > add.precedence);
+    assert!(exponent.precedence > negation.precedence);
+    assert!(negation.precedence > multiply.precedence);
+    assert!(Sine.info().precedence > exponent.precedence);
+    assert_eq!(Associativity::Left, Subtract.info().associativity);
+    assert_eq!(Associativity::Right, exponent.associativity);
+    assert_eq!(2, Divide.info().arity);
+    assert_eq!(1, negation.arity);
+    assert_eq!(Some(add), Op(Add).info());
+    assert_eq!(None, Number(Num::from(1.0)).info());
+    assert_eq!(None, LParen.info());
+}
+
+#[test]
+fn lexer_unop_trig_full() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+
+    for (input, un_op) in [
+        ("csc", Cosecant),
+        ("sec", Secant),
+        ("cot", Cotangent),
+        ("arcsin", ArcSine),
+        ("asin", ArcSine),
+        ("arccos", ArcCosine),
+        ("acos", ArcCosine),
+        ("arctan", ArcTangent),
+        ("atan", ArcTangent),
+        ("arccsc", ArcCosecant),
+        ("arcsec", ArcSecant),
+        ("arccot", ArcCotangent),
+        ("sinh", HyperbolicSine),
+        ("cosh", HyperbolicCosine),
+        ("tanh", HyperbolicTangent),
+        ("asinh", InverseHyperbolicSine),
+        ("arsinh", InverseHyperbolicSine),
+        ("acosh", InverseHyperbolicCosine),
+        ("atanh", InverseHyperbolicTangent),
+    ] {
+        assert_eq!(vec![UnOp(un_op)], Lexer::new_inorder(input).unwrap().list);
+    }
+}
+
+#[test]
+fn lexer_unop_trig_longest_match() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(
+        vec![UnOp(HyperbolicSine), Var(Variable::X)],
+        Lexer::new_inorder("sinhx").unwrap().list
+    );
+    assert_eq!(
+        vec![UnOp(Sine), Var(Variable::X)],
+        Lexer::new_inorder("sinx").unwrap().list
+    );
+    assert_eq!(
+        vec![UnOp(ArcTangent), Var(Variable::Y)],
+        Lexer::new_inorder("arctan y").unwrap().list
+    );
+}
+
+#[test]
+fn unop_trig_apply() {
+    use parse_eq::token::UnaryOperator::*;
+
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+    let x = 0.5_f64;
+    assert!(close(Cosecant.apply(x), 1.0 / x.sin()));
+    assert!(close(Secant.apply(x), 1.0 / x.cos()));
+    assert!(close(Cotangent.apply(x), 1.0 / x.tan()));
+    assert!(close(ArcSine.apply(Sine.apply(x)), x));
+    assert!(close(ArcCosine.apply(Cosine.apply(x)), x));
+    assert!(close(ArcTangent.apply(Tangent.apply(x)), x));
+    assert!(close(ArcCosecant.apply(Cosecant.apply(x)), x));
+    assert!(close(ArcSecant.apply(Secant.apply(x)), x));
+    assert!(close(ArcCotangent.apply(Cotangent.apply(x)), x));
+    assert!(close(
+        InverseHyperbolicSine.apply(HyperbolicSine.apply(x)),
+        x
+    ));
+    assert!(close(
+        InverseHyperbolicCosine.apply(HyperbolicCosine.apply(x)),
+        x
+    ));
+    assert!(close(
+        InverseHyperbolicTangent.apply(HyperbolicTangent.apply(x)),
+        x
+    ));
+    assert!(ArcSine.apply(2.0).is_nan());
+}
+
+#[test]
+fn lexer_logarithm() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(
+        vec![
+            UnOp(NaturalLogarithm),
+            Var(Variable::X),
+            Op(Add),
+            UnOp(CommonLogarithm),
+            Var(Variable::Y),
+            Op(Add),
+            UnOp(BinaryLogarithm),
+            Var(Variable::Z),
+            Op(Add),
+            UnOp(Exponential),
+            Number(Num::from(1.0)),
+        ],
+        Lexer::new_inorder("ln x + log y + log2 z + exp 1")
+            .unwrap()
+            .list
+    );
+    // log10 is another name for the common logarithm.
+    assert_eq!(
+        vec![UnOp(CommonLogarithm), Var(Variable::X)],
+        Lexer::new_inorder("log10 x").unwrap().list
+    );
+    // The base of log_b x is the left operand of the logarithm operator.
+    assert_eq!(
+        vec![Number(Num::from(2.0)), Op(Logarithm), Var(Variable::X)],
+        Lexer::new_inorder("log_2 x").unwrap().list
+    );
+    assert_eq!(
+        vec![
+            Var(Variable::Y),
+            Op(Logarithm),
+            LParen,
+            Var(Variable::X),
+            RParen
+        ],
+        Lexer::new_inorder("log_y(x)").unwrap().list
+    );
+    // The base can have a subscript of its own.
+    assert_eq!(
+        vec![Var(Variable::new("x_1")), Op(Logarithm), Var(Variable::Y)],
+        Lexer::new_inorder("log_x_1 y").unwrap().list
+    );
+    assert_eq!(
+        vec![
+            UnOp(CommonLogarithm),
+            LParen,
+            Number(Num::from(2.0)),
+            Comma,
+            UnOp(Negation),
+            Var(Variable::X),
+            RParen
+        ],
+        Lexer::new_inorder("log(2, -x)").unwrap().list
+    );
+}
+
+#[test]
+fn logarithm_apply() {
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::UnaryOperator::*;
+
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-5;
+    assert!(close(NaturalLogarithm.apply(1.0_f64.exp()), 1.0));
+    assert!(close(CommonLogarithm.apply(1000.0), 3.0));
+    assert!(close(BinaryLogarithm.apply(8.0), 3.0));
+    assert!(close(Exponential.apply(NaturalLogarithm.apply(5.0)), 5.0));
+    assert!(close(Logarithm.apply(3.0, 81.0), 4.0));
+    assert!(close(Exponent.apply(2.0, 10.0), 1024.0));
+    assert!(NaturalLogarithm.apply(-1.0).is_nan());
+}
+
+#[test]
+fn lexer_functions() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Function::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(
+        vec![
+            Func(ArcTangent2),
+            LParen,
+            Var(Variable::Y),
+            Comma,
+            UnOp(Negation),
+            Var(Variable::X),
+            RParen
+        ],
+        Lexer::new_inorder("atan2(y, -x)").unwrap().list
+    );
+    // "atan" followed by any other number is the arc tangent of that number.
+    assert_eq!(
+        vec![UnOp(ArcTangent), Number(Num::from(23.0))],
+        Lexer::new_inorder("atan23").unwrap().list
+    );
+    assert_eq!(
+        vec![Func(Maximum), Func(Minimum), Func(Hypotenuse), Func(Clamp)],
+        Lexer::new_inorder("max min hypot clamp").unwrap().list
+    );
+}
+
+#[test]
+fn function_apply() {
+    use parse_eq::token::Function::*;
+
+    assert_eq!(3.0, Maximum.apply(&[1.0, 3.0, 2.0]));
+    assert_eq!(-1.0, Minimum.apply(&[1.0, -1.0]));
+    assert_eq!(5.0, Hypotenuse.apply(&[3.0, 4.0]));
+    assert_eq!(std::f64::consts::FRAC_PI_2, ArcTangent2.apply(&[1.0, 0.0]));
+    assert_eq!(1.0, Clamp.apply(&[7.0, 0.0, 1.0]));
+    assert_eq!(0.5, Clamp.apply(&[0.5, 0.0, 1.0]));
+    assert!(Maximum.accepts(1) && Maximum.accepts(5) && !Maximum.accepts(0));
+    assert!(ArcTangent2.accepts(2) && !ArcTangent2.accepts(3));
+    assert!(Clamp.accepts(3) && !Clamp.accepts(2));
+}
+
+#[test]
+fn lexer_identifiers() {
+    use parse_eq::lexer::Identifiers;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    let a = Variable::new("a");
+    let b = Variable::new("b");
+    assert_eq!(
+        vec![
+            Var(a),
+            Op(Add),
+            Var(b),
+            Op(Multiply),
+            Var(Variable::new("C"))
+        ],
+        Lexer::new_inorder("a + b * C").unwrap().list
+    );
+    // By default every letter outside of a known word is a variable of its own.
+    assert_eq!(
+        vec![
+            Var(Variable::X),
+            Op(Multiply),
+            Var(Variable::Y),
+            Op(Multiply),
+            UnOp(Sine),
+            Var(a)
+        ],
+        Lexer::new_inorder("xy sina").unwrap().list
+    );
+    assert_eq!(
+        vec![
+            Var(Variable::new("v_0")),
+            Op(Add),
+            Var(a),
+            Op(Multiply),
+            Var(Variable::new("x_12")),
+        ],
+        Lexer::new_inorder("v_0 + ax_12").unwrap().list
+    );
+    // A subscript can have subscripts, all part of the one variable.
+    assert_eq!(
+        vec![
+            Var(Variable::new("a_b_c")),
+            Op(Add),
+            Var(Variable::new("x_i_1"))
+        ],
+        Lexer::new_inorder("a_b_c + x_i_1").unwrap().list
+    );
+    assert_eq!(
+        vec![Var(Variable::new("ж")), Op(Divide), Var(Variable::new("é"))],
+        Lexer::new_inorder("ж / é").unwrap().list
+    );
+
+    // Reading whole words, a run of letters and digits is a single variable.
+    let options = LexerOptions {
+        identifiers: Identifiers::Words,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Var(Variable::new("xy")),
+            Op(Add),
+            Var(Variable::new("rate2")),
+            Op(Multiply),
+            UnOp(Sine),
+            Var(Variable::new("k_max")),
+        ],
+        Lexer::new_inorder_with("xy + rate2 * sin k_max", options)
+            .unwrap()
+            .list
+    );
+    assert_eq!(
+        vec![Var(Variable::new("sinx"))],
+        Lexer::new_inorder_with("sinx", options).unwrap().list
+    );
+}
+
+#[test]
+fn variable_interning() {
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(Variable::new("omega"), Variable::new("omega"));
+    assert_ne!(Variable::new("omega"), Variable::new("Omega"));
+    assert_eq!(Variable::X, Variable::new("x"));
+    assert_eq!("omega", Variable::new("omega").name());
+    assert_eq!("v_0", Var(Variable::new("v_0")).to_string());
+    assert_eq!("Variable(\"y\")", format!("{:?}", Variable::Y));
+}
+
+#[test]
+fn lexer_greek_and_unicode() {
+    use parse_eq::lexer::Identifiers;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    let theta = Variable::new("θ");
+    let omega = Variable::new("ω");
+    // Greek letters can be written as themselves or spelled out, in either identifier mode.
+    for options in [
+        LexerOptions::default(),
+        LexerOptions {
+            identifiers: Identifiers::Words,
+            ..Default::default()
+        },
+    ] {
+        assert_eq!(
+            vec![Var(omega), Op(Multiply), UnOp(Sine), Var(theta)],
+            Lexer::new_inorder_with("omega * sin theta", options)
+                .unwrap()
+                .list
+        );
+        assert_eq!(
+            vec![Var(omega), Op(Multiply), UnOp(Sine), Var(theta)],
+            Lexer::new_inorder_with("ω * sin θ", options).unwrap().list
+        );
+    }
+    assert_eq!(
+        vec![
+            UnOp(Cosine),
+            Var(theta),
+            Op(Multiply),
+            Var(Variable::new("Δ_0"))
+        ],
+        Lexer::new_inorder("costhetaDelta_0").unwrap().list
+    );
+
+    // Spelled out names are only read where they make up the whole run of letters with other
+    // words, and names of two letters are read as two variables.
+    let letters = |input| {
+        let list = Lexer::new_inorder(input).unwrap().list;
+        list.iter().map(|token| token.value).collect::<Vec<_>>()
+    };
+    assert_eq!(letters("x * i"), letters("xi"));
+    assert_eq!(letters("2 * x * i"), letters("2xi"));
+    assert_eq!(letters("m * u * n * u"), letters("munu"));
+    assert_eq!(letters("x * e * t * a"), letters("xeta"));
+    assert_eq!(letters("z * e * t * a * x"), letters("zetax"));
+    assert_eq!(vec![Var(Variable::new("η"))], letters("eta"));
+    assert_eq!(letters("sin η"), letters("sineta"));
+    let words = LexerOptions {
+        identifiers: Identifiers::Words,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![Var(Variable::new("ξ"))],
+        Lexer::new_inorder_with("xi", words).unwrap().list
+    );
+
+    // Typeset operators.
+    assert_eq!(
+        vec![
+            Number(Num::from(2.0)),
+            Op(Multiply),
+            UnOp(SquareRoot),
+            Var(Variable::X),
+            Op(Divide),
+            Number(Num::from(3.0)),
+            Op(Subtract),
+            UnOp(Negation),
+            Number(Num::from(1.0)),
+        ],
+        Lexer::new_inorder("2 × √x ÷ 3 − −1").unwrap().list
+    );
+    assert_eq!(
+        Lexer::new_inorder("π").unwrap().list,
+        Lexer::new_inorder("pi").unwrap().list
+    );
+
+    // Printed with the symbols themselves.
+    let printed: Vec<String> = Lexer::new_inorder("sqrt(theta) + Omega")
+        .unwrap()
+        .list
+        .iter()
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(vec!["√", "(", "θ", ")", "+", "Ω"], printed);
+}
+
+#[test]
+fn lexer_constants() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Constant::*;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(
+        vec![
+            Const(Pi),
+            Op(Add),
+            Const(E),
+            Op(Add),
+            Const(Tau),
+            Op(Add),
+            Const(GoldenRatio),
+            Op(Add),
+            UnOp(Negation),
+            Const(Infinity),
+            Op(Add),
+            Const(Infinity),
+        ],
+        Lexer::new_inorder("pi + e + tau + phi + -inf + ∞")
+            .unwrap()
+            .list
+    );
+    // Words are still read before the constants they contain.
+    assert_eq!(
+        vec![
+            UnOp(Exponential),
+            Var(Variable::X),
+            Op(Multiply),
+            Const(E),
+            Op(Multiply),
+            Var(Variable::X)
+        ],
+        Lexer::new_inorder("expxex").unwrap().list
+    );
+    // The letters are the same constants as the spelled out names, and are how they are printed.
+    assert_eq!(
+        vec![Const(GoldenRatio), Op(Multiply), Const(Tau)],
+        Lexer::new_inorder("φ * τ").unwrap().list
+    );
+    let printed: Vec<String> = [Pi, E, Tau, GoldenRatio, Infinity]
+        .iter()
+        .map(|constant| Const(*constant).to_string())
+        .collect();
+    assert_eq!(vec!["π", "e", "τ", "φ", "∞"], printed);
+    // Only the lower-case letter is the constant.
+    assert_eq!(
+        vec![Var(Variable::new("Φ"))],
+        Lexer::new_inorder("Phi").unwrap().list
+    );
+
+    assert_eq!(std::f64::consts::PI, Pi.value());
+    assert_eq!(2.0 * Pi.value(), Tau.value());
+    assert!((GoldenRatio.value() - (1.0 + 5.0_f64.sqrt()) / 2.0).abs() < 1e-12);
+    assert_eq!(f64::INFINITY, Infinity.value());
+}
+
+#[test]
+fn lexer_imaginary() {
+    use parse_eq::lexer::ImaginaryUnit;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+
+    assert_eq!(
+        vec![
+            Number(Num::from(3)),
+            Op(Add),
+            Imaginary(Num::from(4)),
+            Op(Multiply),
+            Imaginary(Num::from(1)),
+        ],
+        Lexer::new_inorder("3+4i * i").unwrap().list
+    );
+    // Only directly after the number, and not when starting a longer word.
+    assert_eq!(
+        vec![
+            Number(Num::from(2)),
+            Op(Multiply),
+            Var(Variable::new("i_0"))
+        ],
+        Lexer::new_inorder("2i_0").unwrap().list
+    );
+
+    let options = LexerOptions {
+        imaginary_unit: ImaginaryUnit::J,
+        ..Default::default()
+    };
+    assert_eq!(
+        vec![
+            Imaginary(Num::from(0.5)),
+            Op(Subtract),
+            Imaginary(Num::from(1)),
+            Op(Multiply),
+            Var(Variable::new("i")),
+        ],
+        Lexer::new_inorder_with("0.5j - j * i", options)
+            .unwrap()
+            .list
+    );
+
+    let printed: Vec<String> = Lexer::new_inorder("2.5i + i")
+        .unwrap()
+        .list
+        .iter()
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(vec!["2.5i", "+", "i"], printed);
+}
+
+#[test]
+fn complex_arithmetic() {
+    use parse_eq::num::Complex;
+    use parse_eq::num::Numeric;
+    use parse_eq::token::UnaryOperator::*;
+
+    let close = |a: Complex, b: Complex| (a - b).norm() < 1e-12;
+    let z = Complex::new(3.0, 4.0);
+    assert_eq!(5.0, z.norm());
+    assert_eq!(Complex::new(-7.0, 24.0), z * z);
+    assert!(close(Complex::new(1.0, 0.0), z / z));
+    assert!(close(z, z.sqrt() * z.sqrt()));
+    assert!(close(z, z.ln().exp()));
+    let w = Complex::new(0.5, 0.2);
+    assert!(close(w, ArcSine.apply_num(Sine.apply_num(w))));
+    assert!(close(w, ArcTangent.apply_num(Tangent.apply_num(w))));
+    assert!(close(
+        w,
+        InverseHyperbolicCosine.apply_num(HyperbolicCosine.apply_num(w))
+    ));
+    // Functions outside of their real domain.
+    let asin = ArcSine.apply_num(Complex::from_f64(2.0));
+    assert!(close(Complex::from_f64(2.0), asin.sin()));
+    assert!(close(Complex::I, (-Complex::from_f64(1.0)).sqrt()));
+    // Integer powers are exact.
+    assert_eq!(
+        Complex::from_f64(-1.0),
+        Complex::I.pow(Complex::from_f64(2.0))
+    );
+    assert_eq!(
+        Complex::new(0.0, -1.0),
+        Complex::I.pow(Complex::from_f64(3.0))
+    );
+    assert_eq!(
+        Complex::new(0.0, -1.0),
+        Complex::I.pow(Complex::from_f64(-1.0))
+    );
+    assert_eq!(Complex::new(-7.0, 24.0), z.pow(Complex::from_f64(2.0)));
+    assert_eq!("3+4i", z.to_string());
+    assert_eq!("2-0.5i", Complex::new(2.0, -0.5).to_string());
+    assert!(Complex::I.to_f64().is_nan());
+}
+
+#[test]
+fn lexer_number_literals() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Constant;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+
+    for (input, number) in [
+        ("6.02e23", Num::from(602) * Num::from(10).pow(Num::from(21))),
+        ("1E-9", Num::from(1) / Num::from(1_000_000_000)),
+        ("2.5e+2", Num::from(250)),
+        ("1_000_000", Num::from(1_000_000)),
+        (".5", Num::from(0.5)),
+        ("0x1F", Num::from(31)),
+        ("0xff_ff", Num::from(65535)),
+        ("0b1010", Num::from(10)),
+        ("0o17", Num::from(15)),
+        ("1e400", Num::from(10).pow(Num::from(400))),
+    ] {
+        assert_eq!(
+            vec![Number(number)],
+            Lexer::new_inorder(input).unwrap().list,
+            "{}",
+            input
+        );
+    }
+    // e is only an exponent when followed by digits, otherwise it is Euler's number.
+    assert_eq!(
+        vec![Number(Num::from(2)), Op(Multiply), Const(Constant::E)],
+        Lexer::new_inorder("2e").unwrap().list
+    );
+    assert_eq!(
+        vec![
+            Number(Num::from(2)),
+            Op(Multiply),
+            Const(Constant::E),
+            Op(Subtract),
+            Var(Variable::X)
+        ],
+        Lexer::new_inorder("2e-x").unwrap().list
+    );
+    assert_eq!(
+        vec![Imaginary(Num::from(2000))],
+        Lexer::new_inorder("2e3i").unwrap().list
+    );
+}
+
+#[test]
+fn lexer_implicit_multiplication() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Span;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+
+    let list = Lexer::new_inorder("2x").unwrap().list;
+    assert_eq!(
+        vec![Number(Num::from(2)), Op(Multiply), Var(Variable::X)],
+        list
+    );
+    // The inserted multiplication was not written, so its span is empty.
+    assert_eq!(Span::new(1, 1), list[1].span);
+    assert!(list[1].span.is_empty());
+
+    for (implicit, explicit) in [
+        ("3(x+1)", "3*(x+1)"),
+        ("(a+b)(a-b)", "(a+b)*(a-b)"),
+        ("x sin(y)", "x*sin(y)"),
+        ("2 max(x, 1)", "2*max(x, 1)"),
+        ("-2πr", "-2*π*r"),
+        ("2 log_3 x", "2 * log_3 x"),
+        ("x y^2", "x*y^2"),
+    ] {
+        assert_eq!(
+            Lexer::new_inorder(explicit)
+                .unwrap()
+                .list
+                .iter()
+                .map(|token| token.value)
+                .collect::<Vec<_>>(),
+            Lexer::new_inorder(implicit).unwrap().list,
+            "{}",
+            implicit
+        );
+    }
+    // Numbers are not multiplied with each other, nor after anything else.
+    assert_eq!(
+        vec![Number(Num::from(1)), Number(Num::from(2))],
+        Lexer::new_inorder("1 2").unwrap().list
+    );
+    assert_eq!(
+        vec![Var(Variable::X), Number(Num::from(2))],
+        Lexer::new_inorder("x 2").unwrap().list
+    );
+    assert_eq!(
+        vec![Var(Variable::X), Number(Num::from(2))],
+        Lexer::new_inorder("x2").unwrap().list
+    );
+    assert_eq!(
+        vec![RParen, Number(Num::from(2))],
+        Lexer::new_inorder("(a+b)2").unwrap().list[4..]
+    );
+}
@@ -9,12 +9,13 @@ use std::io::Read;
 fn tree_simple() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::tree::Tree;
 
-    let left_node = TreeNode::new_rc(Number(1.0), None, None);
-    let right_node = TreeNode::new_rc(Number(2.0), None, None);
+    let left_node = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let right_node = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
     let check_tree: Tree = Tree::new(TreeNode::new_rc(
         Op(Divide),
         Some(left_node),
@@ -23,7 +24,7 @@ fn tree_simple() {
     let lexer = Lexer::new_inorder("1/2").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -31,13 +32,14 @@ fn tree_simple() {
 fn tree_parens_simple() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::tree::Tree;
 
     // 2 * ( 5 * 3 + 4 / ( 1 + 6 ) )
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
 
     let add = TreeNode::new_rc(Op(Add), Some(one), Some(two));
     let check_tree: Tree = Tree::new(add);
@@ -45,10 +47,16 @@ fn tree_parens_simple() {
     let lexer = Lexer::new_inorder("(1 + 2)").unwrap();
     let in_order = lexer.list;
 
-    let check_in_order = vec![LParen, Number(1.0), Op(Add), Number(2.0), RParen];
+    let check_in_order = vec![
+        LParen,
+        Number(Num::from(1.0)),
+        Op(Add),
+        Number(Num::from(2.0)),
+        RParen,
+    ];
     assert_eq!(in_order, check_in_order);
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -56,17 +64,18 @@ fn tree_parens_simple() {
 fn tree_parens() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::tree::Tree;
 
     // 2 * ( 5 * 3 + 4 / ( 1 + 6 ) )
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
-    let three = TreeNode::new_rc(Number(3.0), None, None);
-    let four = TreeNode::new_rc(Number(4.0), None, None);
-    let five = TreeNode::new_rc(Number(5.0), None, None);
-    let six = TreeNode::new_rc(Number(6.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let three = TreeNode::new_rc(Number(Num::from(3.0)), None, None);
+    let four = TreeNode::new_rc(Number(Num::from(4.0)), None, None);
+    let five = TreeNode::new_rc(Number(Num::from(5.0)), None, None);
+    let six = TreeNode::new_rc(Number(Num::from(6.0)), None, None);
 
     let lower_plus = TreeNode::new_rc(Op(Add), Some(one), Some(six));
     let division = TreeNode::new_rc(Op(Divide), Some(four), Some(lower_plus));
@@ -78,7 +87,7 @@ fn tree_parens() {
     let lexer = Lexer::new_inorder("2 * ( 5 * 3 + 4 / ( 1 + 6 ) )").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -86,17 +95,18 @@ fn tree_parens() {
 fn tree_parens_many() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::tree::Tree;
 
     // 2 * ( 5 * 3 + 4 / ( 1 + 6 ) )
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
-    let three = TreeNode::new_rc(Number(3.0), None, None);
-    let four = TreeNode::new_rc(Number(4.0), None, None);
-    let five = TreeNode::new_rc(Number(5.0), None, None);
-    let six = TreeNode::new_rc(Number(6.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let three = TreeNode::new_rc(Number(Num::from(3.0)), None, None);
+    let four = TreeNode::new_rc(Number(Num::from(4.0)), None, None);
+    let five = TreeNode::new_rc(Number(Num::from(5.0)), None, None);
+    let six = TreeNode::new_rc(Number(Num::from(6.0)), None, None);
 
     let lower_plus = TreeNode::new_rc(Op(Add), Some(one), Some(six));
     let division = TreeNode::new_rc(Op(Divide), Some(four), Some(lower_plus));
@@ -108,7 +118,7 @@ fn tree_parens_many() {
     let lexer = Lexer::new_inorder("( ( 2 * ( 5 * 3 + 4 / ( 1 + 6 ) ) ) )").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -116,17 +126,18 @@ fn tree_parens_many() {
 fn tree_parens_many_more() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::tree::Tree;
 
     // 2 * ( 5 * 3 + 4 / ( 1 + 6 ) )
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
-    let three = TreeNode::new_rc(Number(3.0), None, None);
-    let four = TreeNode::new_rc(Number(4.0), None, None);
-    let five = TreeNode::new_rc(Number(5.0), None, None);
-    let six = TreeNode::new_rc(Number(6.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let three = TreeNode::new_rc(Number(Num::from(3.0)), None, None);
+    let four = TreeNode::new_rc(Number(Num::from(4.0)), None, None);
+    let five = TreeNode::new_rc(Number(Num::from(5.0)), None, None);
+    let six = TreeNode::new_rc(Number(Num::from(6.0)), None, None);
 
     let lower_plus = TreeNode::new_rc(Op(Add), Some(one), Some(six));
     let division = TreeNode::new_rc(Op(Divide), Some(four), Some(lower_plus));
@@ -138,7 +149,7 @@ fn tree_parens_many_more() {
     let lexer = Lexer::new_inorder("( ( 2 * ( ( 5 * 3 ) + 4 / ( ( 1 ) + ( 6 ) ) ) ) )").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -146,12 +157,13 @@ fn tree_parens_many_more() {
 fn tree_var_simple() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
 
     let x_plus_one = TreeNode::new_rc(Op(Add), Some(x), Some(one));
@@ -160,7 +172,7 @@ fn tree_var_simple() {
     let lexer = Lexer::new_inorder("x + 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 }
 
@@ -168,11 +180,12 @@ fn tree_var_simple() {
 fn tree_unary_simple() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
     let sine_one = TreeNode::new_rc(UnOp(Sine), None, Some(one));
 
     let check_tree = Tree::new(sine_one);
@@ -180,7 +193,7 @@ fn tree_unary_simple() {
     let lexer = Lexer::new_inorder("sin 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(check_tree, tree);
 }
 
@@ -188,13 +201,14 @@ fn tree_unary_simple() {
 fn tree_unary_with_var() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
     let sine_x = TreeNode::new_rc(UnOp(Sine), None, Some(x));
 
@@ -205,7 +219,7 @@ fn tree_unary_with_var() {
     let lexer = Lexer::new_inorder("sin x + 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(check_tree, tree);
 }
 
@@ -213,12 +227,13 @@ fn tree_unary_with_var() {
 fn tree_var_parens() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
 
     let x_plus_one = TreeNode::new_rc(Op(Add), Some(x), Some(one));
@@ -227,25 +242,25 @@ fn tree_var_parens() {
     let lexer = Lexer::new_inorder("( x ) + 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 
     let lexer2 = Lexer::new_inorder("(x) + 1").unwrap();
     let in_order2 = lexer2.list;
 
-    let tree2: Tree = Tree::new_pre_from_in(in_order2);
+    let tree2: Tree = Tree::new_pre_from_in(in_order2).unwrap();
     assert_eq!(tree2, check_tree);
 
     let lexer3 = Lexer::new_inorder("x + (1)").unwrap();
     let in_order3 = lexer3.list;
 
-    let tree3: Tree = Tree::new_pre_from_in(in_order3);
+    let tree3: Tree = Tree::new_pre_from_in(in_order3).unwrap();
     assert_eq!(tree3, check_tree);
 
     let lexer3 = Lexer::new_inorder("(x + 1)").unwrap();
     let in_order3 = lexer3.list;
 
-    let tree3: Tree = Tree::new_pre_from_in(in_order3);
+    let tree3: Tree = Tree::new_pre_from_in(in_order3).unwrap();
     assert_eq!(tree3, check_tree);
 }
 
@@ -253,13 +268,14 @@ fn tree_var_parens() {
 fn tree_unary_parens() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
     let sin_x = TreeNode::new_rc(UnOp(Sine), None, Some(x));
 
@@ -269,31 +285,31 @@ fn tree_unary_parens() {
     let lexer = Lexer::new_inorder("( sin x ) + 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     assert_eq!(tree, check_tree);
 
     let lexer2 = Lexer::new_inorder("(sin x) + 1").unwrap();
     let in_order2 = lexer2.list;
 
-    let tree2: Tree = Tree::new_pre_from_in(in_order2);
+    let tree2: Tree = Tree::new_pre_from_in(in_order2).unwrap();
     assert_eq!(tree2, check_tree);
 
     let lexer3 = Lexer::new_inorder("sin x + (1)").unwrap();
     let in_order3 = lexer3.list;
 
-    let tree3: Tree = Tree::new_pre_from_in(in_order3);
+    let tree3: Tree = Tree::new_pre_from_in(in_order3).unwrap();
     assert_eq!(tree3, check_tree);
 
     let lexer4 = Lexer::new_inorder("(sin x + 1)").unwrap();
     let in_order4 = lexer4.list;
 
-    let tree4: Tree = Tree::new_pre_from_in(in_order4);
+    let tree4: Tree = Tree::new_pre_from_in(in_order4).unwrap();
     assert_eq!(tree4, check_tree);
 
     let lexer5 = Lexer::new_inorder("(sin (x) + 1)").unwrap();
     let in_order5 = lexer5.list;
 
-    let tree5: Tree = Tree::new_pre_from_in(in_order5);
+    let tree5: Tree = Tree::new_pre_from_in(in_order5).unwrap();
     assert_eq!(tree5, check_tree);
 }
 
@@ -301,15 +317,16 @@ fn tree_unary_parens() {
 fn tree_unary_complex() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
-    let five = TreeNode::new_rc(Number(5.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let five = TreeNode::new_rc(Number(Num::from(5.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
     let sin_x = TreeNode::new_rc(UnOp(Sine), None, Some(x));
 
@@ -321,7 +338,7 @@ fn tree_unary_complex() {
     let lexer = Lexer::new_inorder("2 - (5 * sin x ) + 1").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
 
     assert_eq!(check_tree, tree);
 }
@@ -330,15 +347,16 @@ fn tree_unary_complex() {
 fn tree_unary_negation() {
     use binary_tree_ds::TreeNode;
     use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
     use parse_eq::token::Operator::*;
     use parse_eq::token::Token::*;
     use parse_eq::token::UnaryOperator::*;
     use parse_eq::token::Variable;
     use parse_eq::tree::Tree;
 
-    let one = TreeNode::new_rc(Number(1.0), None, None);
-    let two = TreeNode::new_rc(Number(2.0), None, None);
-    let five = TreeNode::new_rc(Number(5.0), None, None);
+    let one = TreeNode::new_rc(Number(Num::from(1.0)), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let five = TreeNode::new_rc(Number(Num::from(5.0)), None, None);
     let x = TreeNode::new_rc(Var(Variable::X), None, None);
 
     let neg_x = TreeNode::new_rc(UnOp(Negation), None, Some(x));
@@ -355,7 +373,7 @@ fn tree_unary_negation() {
     let in_order = lexer.list;
     println!("lexer.list: {:?}", in_order);
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     tree.save_typst_tree("typst_test2.typ").unwrap();
 
     assert_eq!(check_tree, tree);
@@ -372,7 +390,7 @@ fn tree_save_typst() {
     let lexer = Lexer::new_inorder("-2 * ( 5 * 3 + 4 / ( 1 + -sin -(x/2) ) )").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     tree.save_typst_tree("typst_test.typ").unwrap();
 
     let mut file = File::open("./typst_test.typ").unwrap();
@@ -382,7 +400,7 @@ fn tree_save_typst() {
         contents,
         r#"
 #let data = (
-([\*], ([-], [2.000]), ([+], ([\*], [5.000], [3.000]), ([/], [4.000], ([+], [1.000], ([-], ([sin], ([-], ([/], [x], [2.000]))))))))
+([\*], ([-], [2]), ([+], ([\*], [5], [3]), ([/], [4], ([+], [1], ([-], ([sin], ([-], ([/], [x], [2]))))))))
 )
 
 #import "@preview/cetz:0.1.2": canvas, draw, tree
@@ -415,7 +433,7 @@ fn tree_create_vec() {
     let lexer = Lexer::new_inorder("-2 * ( 5 * 3 + 4 / ( 1 + -sin -(x/2) ) )").unwrap();
     let in_order = lexer.list;
 
-    let tree: Tree = Tree::new_pre_from_in(in_order);
+    let tree: Tree = Tree::new_pre_from_in(in_order).unwrap();
     let in_order_vec = tree.create_vec(Ordering::In);
 
     let check_lexer = Lexer::new_inorder("-2 * ( 5 * 3 + 4 / ( 1 + -sin -(x/2) ) )").unwrap();
@@ -426,3 +444,899 @@ fn tree_create_vec() {
     }
     assert_eq!(check_in_order, in_order_vec);
 }
+
+#[test]
+fn tree_spans() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let input = "2 * (x + 1) - sin y";
+    let lexer = Lexer::new_inorder(input).unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+
+    let root = tree.root().clone();
+    assert_eq!(Some(Span::new(0, 19)), tree.span_of(&root));
+
+    let times = root.borrow().left.clone().unwrap();
+    assert_eq!(Some(Span::new(0, 11)), tree.span_of(&times));
+
+    let plus = times.borrow().right.clone().unwrap();
+    let plus_span = tree.span_of(&plus).unwrap();
+    assert_eq!("(x + 1)", &input[plus_span.start..plus_span.end]);
+
+    let sine = root.borrow().right.clone().unwrap();
+    assert_eq!(Some(Span::new(14, 19)), tree.span_of(&sine));
+
+    let y = sine.borrow().right.clone().unwrap();
+    assert_eq!(Some(Span::new(18, 19)), tree.span_of(&y));
+}
+
+#[test]
+fn tree_spans_not_from_string() {
+    use binary_tree_ds::TreeNode;
+    use parse_eq::num::Num;
+    use parse_eq::token::Token::*;
+    use parse_eq::tree::Tree;
+
+    let tree = Tree::new(TreeNode::new_rc(Number(Num::from(1.0)), None, None));
+    assert_eq!(None, tree.span_of(tree.root()));
+}
+
+#[test]
+fn tree_exponent_right_associative() {
+    use binary_tree_ds::TreeNode;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::tree::Tree;
+
+    // 2^3^2 == 2^(3^2)
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let three = TreeNode::new_rc(Number(Num::from(3.0)), None, None);
+    let other_two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let upper = TreeNode::new_rc(Op(Exponent), Some(three), Some(other_two));
+    let check_tree = Tree::new(TreeNode::new_rc(Op(Exponent), Some(two), Some(upper)));
+
+    let lexer = Lexer::new_inorder("2^3^2").unwrap();
+    assert_eq!(check_tree, Tree::new_pre_from_in(lexer.list).unwrap());
+    let lexer = Lexer::new_inorder("2^(3^2)").unwrap();
+    assert_eq!(check_tree, Tree::new_pre_from_in(lexer.list).unwrap());
+}
+
+#[test]
+fn tree_exponent_priority() {
+    use binary_tree_ds::TreeNode;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    // -x^2 == -(x^2)
+    let x = TreeNode::new_rc(Var(Variable::X), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let square = TreeNode::new_rc(Op(Exponent), Some(x), Some(two));
+    let check_tree = Tree::new(TreeNode::new_rc(UnOp(Negation), None, Some(square.clone())));
+    let lexer = Lexer::new_inorder("-x^2").unwrap();
+    assert_eq!(check_tree, Tree::new_pre_from_in(lexer.list).unwrap());
+
+    // 3 * x^2 == 3 * (x^2)
+    let three = TreeNode::new_rc(Number(Num::from(3.0)), None, None);
+    let check_tree = Tree::new(TreeNode::new_rc(Op(Multiply), Some(three), Some(square)));
+    let lexer = Lexer::new_inorder("3 * x^2").unwrap();
+    assert_eq!(check_tree, Tree::new_pre_from_in(lexer.list).unwrap());
+
+    // sin x^2 == (sin x)^2
+    let x = TreeNode::new_rc(Var(Variable::X), None, None);
+    let two = TreeNode::new_rc(Number(Num::from(2.0)), None, None);
+    let sine = TreeNode::new_rc(UnOp(Sine), None, Some(x));
+    let check_tree = Tree::new(TreeNode::new_rc(Op(Exponent), Some(sine), Some(two)));
+    let lexer = Lexer::new_inorder("sin x^2").unwrap();
+    assert_eq!(check_tree, Tree::new_pre_from_in(lexer.list).unwrap());
+}
+
+#[test]
+fn tree_exponent_create_vec() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::tree::Tree;
+
+    for input in [
+        "2 ^ 3 ^ 2",
+        "(2 ^ 3) ^ 2",
+        "-x ^ 2",
+        "(-x) ^ 2",
+        "2 ^ -x",
+        "(x + 1) ^ (y * 2)",
+        "3 * x ^ 2 - sin (x ^ 2)",
+    ] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        let check_in_order = Lexer::new_inorder(input).unwrap().list;
+        let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+        assert_eq!(check_in_order, tree.create_vec(Ordering::In), "{}", input);
+    }
+}
+
+#[test]
+fn tree_create_vec_associativity() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::tree::Tree;
+
+    // Parentheses which change the meaning of the expression are kept.
+    for input in [
+        "x - (y - z)",
+        "x - (y + z)",
+        "x / (y / z)",
+        "x / (y * z)",
+        "x - y - z",
+        "x / y / z",
+        "(x - y) * z",
+    ] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        let check_in_order = Lexer::new_inorder(input).unwrap().list;
+        let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+        assert_eq!(check_in_order, tree.create_vec(Ordering::In), "{}", input);
+    }
+
+    // Parentheses which do not are left out.
+    for (input, check) in [
+        ("x + (y - z)", "x + y - z"),
+        ("x * (y / z)", "x * y / z"),
+        ("(x - y) - z", "x - y - z"),
+    ] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        let check_in_order = Lexer::new_inorder(check).unwrap().list;
+        let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+        assert_eq!(check_in_order, tree.create_vec(Ordering::In), "{}", input);
+    }
+}
+
+#[test]
+fn tree_create_vec_post() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::tree::Tree;
+
+    // Only the in-order list needs parentheses.
+    let lexer = Lexer::new_inorder("2 * (1 + 3)").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(
+        vec![
+            Number(Num::from(2.0)),
+            Number(Num::from(1.0)),
+            Number(Num::from(3.0)),
+            Op(Add),
+            Op(Multiply)
+        ],
+        tree.create_vec(Ordering::Post)
+    );
+    assert_eq!(
+        vec![
+            Op(Multiply),
+            Number(Num::from(2.0)),
+            Op(Add),
+            Number(Num::from(1.0)),
+            Number(Num::from(3.0))
+        ],
+        tree.create_vec(Ordering::Pre)
+    );
+}
+
+#[test]
+fn tree_unary_trig_create_vec() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::tree::Tree;
+
+    let input = "arcsin (x / 2) + cosh y ^ 2 - sec -tanh z";
+    let lexer = Lexer::new_inorder(input).unwrap();
+    let check_in_order = Lexer::new_inorder(input).unwrap().list;
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(check_in_order, tree.create_vec(Ordering::In));
+
+    let printed: Vec<String> = tree
+        .create_vec(Ordering::Pre)
+        .iter()
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(
+        vec!["-", "+", "arcsin", "/", "x", "2", "^", "cosh", "y", "2", "sec", "-", "tanh", "z"],
+        printed
+    );
+}
+
+#[test]
+fn tree_logarithm() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    // log(b, x), log_b(x) and log_b x all become the same logarithm operator.
+    for input in ["log(2, x + 1)", "log_2(x + 1)", "log_2 (x + 1)"] {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+        assert_eq!(
+            vec![
+                Op(Logarithm),
+                Number(Num::from(2.0)),
+                Op(Add),
+                Var(Variable::X),
+                Number(Num::from(1.0))
+            ],
+            tree.create_vec(Ordering::Pre),
+            "{}",
+            input
+        );
+        assert_eq!(
+            Lexer::new_inorder("log(2, x + 1)").unwrap().list,
+            tree.create_vec(Ordering::In)
+        );
+    }
+
+    // log_b binds tighter than a function, and is right associative like the exponent.
+    let lexer = Lexer::new_inorder("ln log_2 x * 3").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(
+        vec![
+            Op(Multiply),
+            UnOp(NaturalLogarithm),
+            Op(Logarithm),
+            Number(Num::from(2.0)),
+            Var(Variable::X),
+            Number(Num::from(3.0))
+        ],
+        tree.create_vec(Ordering::Pre)
+    );
+}
+
+#[test]
+fn tree_functions() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::num::Num;
+    use parse_eq::token::Function::*;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+
+    let input = "max(x, y + 1, hypot(x, y)) ^ 2 * clamp(z, 0, 1)";
+    let lexer = Lexer::new_inorder(input).unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(
+        Lexer::new_inorder(input).unwrap().list,
+        tree.create_vec(Ordering::In)
+    );
+
+    // The function node holds all of its arguments.
+    let root = tree.root().borrow();
+    let power = root.left.as_ref().unwrap().borrow();
+    let max = power.left.as_ref().unwrap();
+    assert_eq!(Func(Maximum), max.borrow().value);
+    let arguments = Tree::arguments(max.borrow().right.as_ref().unwrap());
+    let arguments: Vec<Vec<_>> = arguments
+        .iter()
+        .map(|argument| Tree::new(argument.clone()).create_vec(Ordering::Pre))
+        .collect();
+    assert_eq!(
+        vec![
+            vec![Var(Variable::X)],
+            vec![Op(Add), Var(Variable::Y), Number(Num::from(1.0))],
+            vec![Func(Hypotenuse), Comma, Var(Variable::X), Var(Variable::Y)],
+        ],
+        arguments
+    );
+
+    // A single argument needs no parentheses, but gets them back when printed.
+    let lexer = Lexer::new_inorder("min x").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(
+        Lexer::new_inorder("min(x)").unwrap().list,
+        tree.create_vec(Ordering::In)
+    );
+}
+
+#[test]
+fn tree_save_typst_symbols() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::tree::Tree;
+
+    let lexer = Lexer::new_inorder("max(1, x, atan2(y, 2))").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    tree.save_typst_tree("typst_functions_test.typ").unwrap();
+
+    let contents = std::fs::read_to_string("typst_functions_test.typ").unwrap();
+    std::fs::remove_file("typst_functions_test.typ").unwrap();
+    assert!(contents.contains("\n([max], [1], [x], ([atan2], [y], [2]))\n"));
+
+    let lexer = Lexer::new_inorder("v_0 * t").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    tree.save_typst_tree("typst_subscript_test.typ").unwrap();
+
+    let contents = std::fs::read_to_string("typst_subscript_test.typ").unwrap();
+    std::fs::remove_file("typst_subscript_test.typ").unwrap();
+    assert!(contents.contains("\n([\\*], [v\\_0], [t])\n"));
+
+    let lexer = Lexer::new_inorder("sqrt(alpha^2 + beta^2)").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    tree.save_typst_tree("typst_greek_test.typ").unwrap();
+
+    let contents = std::fs::read_to_string("typst_greek_test.typ").unwrap();
+    std::fs::remove_file("typst_greek_test.typ").unwrap();
+    assert!(contents.contains("\n([√], ([+], ([^], [α], [2]), ([^], [β], [2])))\n"));
+}
+
+#[test]
+fn tree_constants() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::num::Num;
+    use parse_eq::token::Constant::*;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::UnaryOperator::*;
+    use parse_eq::tree::Tree;
+
+    // Constants are kept as they are, rather than turned into numbers.
+    let lexer = Lexer::new_inorder("sin(pi) + e^2").unwrap();
+    let tree: Tree = Tree::new_pre_from_in(lexer.list).unwrap();
+    assert_eq!(
+        vec![
+            Op(Add),
+            UnOp(Sine),
+            Const(Pi),
+            Op(Exponent),
+            Const(E),
+            Number(Num::from(2.0))
+        ],
+        tree.create_vec(Ordering::Pre)
+    );
+
+    tree.save_typst_tree("typst_constants_test.typ").unwrap();
+    let contents = std::fs::read_to_string("typst_constants_test.typ").unwrap();
+    std::fs::remove_file("typst_constants_test.typ").unwrap();
+    assert!(contents.contains("\n([+], ([sin], [π]), ([^], [e], [2]))\n"));
+}
+
+#[test]
+fn tree_evaluate_complex() {
+    use parse_eq::bindings::Bindings;
+    use parse_eq::error::EvalError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Complex;
+    use parse_eq::num::Num;
+    use parse_eq::num::Numeric;
+    use parse_eq::tree::Tree;
+
+    let evaluate = |input: &str| {
+        let lexer = Lexer::new_inorder(input).unwrap();
+        Tree::new_pre_from_in(lexer.list).unwrap()
+    };
+    let close = |a: Complex, b: Complex| (a - b).norm() < 1e-12;
+
+    assert_eq!(
+        Some(Complex::new(3.0, 4.0)),
+        evaluate("3 + 4i").evaluate::<Complex>()
+    );
+    // An imaginary literal is a single number, so 2^4i is not (2^4) * i.
+    assert!(close(
+        Complex::new((4.0 * 2.0_f64.ln()).cos(), (4.0 * 2.0_f64.ln()).sin()),
+        evaluate("2^4i").evaluate().unwrap()
+    ));
+    assert!(close(Complex::I, evaluate("sqrt(-1)").evaluate().unwrap()));
+    assert_eq!(
+        Some(Complex::from_f64(-1.0)),
+        evaluate("i^2").evaluate::<Complex>()
+    );
+    assert!(close(
+        Complex::from_f64(-1.0),
+        evaluate("e^(i*pi)").evaluate().unwrap()
+    ));
+    assert!(close(
+        Complex::new(2.0_f64.ln(), std::f64::consts::PI),
+        evaluate("ln(-2)").evaluate().unwrap()
+    ));
+
+    // The same expressions are not real numbers.
+    assert_eq!(None, evaluate("sqrt(-1)").evaluate::<f64>());
+    assert_eq!(None, evaluate("3 + 4i").evaluate::<f64>());
+    assert_eq!(Some(0.5), evaluate("max(1, 2) / 4").evaluate::<f64>());
+    assert_eq!(
+        Some(Num::from(1) / Num::from(2)),
+        evaluate("1/3 + 1/6").evaluate::<Num>()
+    );
+    assert_eq!(None, evaluate("x + 1").evaluate::<f64>());
+
+    // Functions of real numbers are not defined for imaginary arguments.
+    for (input, index) in [
+        ("max(i, 2)", 3),
+        ("min(2, 3i)", 3),
+        ("clamp(i, 0, 1)", 5),
+        ("atan2(1, i)", 3),
+        ("hypot(i, 1)", 3),
+    ] {
+        assert_eq!(
+            Err(EvalError::Domain { index }),
+            evaluate(input).evaluate_with::<Complex>(&Bindings::new()),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn tree_implicit_multiplication() {
+    use parse_eq::lexer::ImplicitMultiplication;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::LexerOptions;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::token::Span;
+    use parse_eq::tree::Tree;
+
+    let tight = LexerOptions {
+        implicit_multiplication: ImplicitMultiplication::Tight,
+        ..Default::default()
+    };
+    for (input, standard, grouped) in [
+        ("1/2x", "(1/2)*x", "1/(2*x)"),
+        ("1/2x^2 + 1", "(1/2)*x^2 + 1", "1/(2*x^2) + 1"),
+        ("a/bc * d", "(a/b)*c * d", "(a/(b*c)) * d"),
+        ("6/2(1+2)", "(6/2)*(1+2)", "6/(2*(1+2))"),
+        ("1/(2x)y", "(1/(2*x))*y", "1/((2*x)*y)"),
+        ("max(1/2x, 1)", "max((1/2)*x, 1)", "max(1/(2*x), 1)"),
+    ] {
+        let parse = |input, options| {
+            Tree::new_pre_from_in(Lexer::new_inorder_with(input, options).unwrap().list).unwrap()
+        };
+        assert_eq!(
+            parse(standard, LexerOptions::default()),
+            parse(input, LexerOptions::default()),
+            "{}",
+            input
+        );
+        assert_eq!(
+            parse(grouped, LexerOptions::default()),
+            parse(input, tight),
+            "{}",
+            input
+        );
+    }
+
+    // The implicit multiplication is printed explicitly, and spans the factors it multiplies.
+    let tree = Tree::new_pre_from_in(Lexer::new_inorder("1 + 2x").unwrap().list).unwrap();
+    assert_eq!(
+        Lexer::new_inorder("1 + 2 * x").unwrap().list,
+        tree.create_vec(Ordering::In)
+    );
+    let product = tree.root().borrow().right.clone().unwrap();
+    assert_eq!(Some(Span::new(4, 6)), tree.span_of(&product));
+}
+
+#[test]
+fn tree_eval() {
+    use parse_eq::bindings::Bindings;
+    use parse_eq::error::EvalError;
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Tree;
+    use parse_eq::tree::Value;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    let a = Variable::new("a");
+    let bindings = Bindings::new().with(a, 3.0).with(Variable::Y, 0.5);
+
+    // Numbers stay exact.
+    assert_eq!(
+        Ok(Value::Number(Num::from(1) / Num::from(3))),
+        parse("1/3 + 1/6 - 1/6").eval(&bindings)
+    );
+    assert_eq!(
+        Ok(Value::Number(Num::from(7))),
+        parse("2a + max(y, 1)").eval(&bindings)
+    );
+
+    // Only the parts without unbound variables are worked out.
+    for (input, reduced) in [
+        ("2 * a + x / 4", "6 + x / 4"),
+        ("x^(a - 1) * y", "x^2 * 0.5"),
+        ("max(a, x, 2 * y) + sin(0)", "max(3, x, 1) + 0"),
+        ("log_a(x) + z", "log(3, x) + z"),
+        ("x + 2i * a", "x + 2i * 3"),
+        // Constants are kept, with the exact values of functions at them worked out.
+        ("x * pi / 2 + sin(pi)", "x * pi / 2 + 0"),
+        ("e^x - ln(e) * a", "e^x - 3"),
+    ] {
+        assert_eq!(
+            Ok(Value::Partial(parse(reduced))),
+            parse(input).eval(&bindings),
+            "{}",
+            input
+        );
+    }
+
+    // Without unbound variables the constants are turned into numbers, exactly where they can be.
+    assert_eq!(
+        Ok(Value::Number(Num::from(0))),
+        parse("sin(pi)").eval(&bindings)
+    );
+    assert_eq!(
+        Ok(Value::Number(Num::from(2))),
+        parse("cos(pi) + a").eval(&bindings)
+    );
+    assert_eq!(
+        Ok(Value::Number(Num::from(std::f64::consts::TAU))),
+        parse("2pi").eval(&bindings)
+    );
+
+    // Imaginary numbers have no value as a `Num`.
+    assert_eq!(
+        Err(EvalError::Domain { index: 1 }),
+        parse("3 + 4i").eval(&bindings)
+    );
+    assert_eq!(
+        Err(EvalError::Domain { index: 0 }),
+        parse("i^2").eval(&bindings)
+    );
+
+    // Indices are those of the tokens in post-order, 2 a y - / here.
+    assert_eq!(
+        Err(EvalError::DivisionByZero { index: 4 }),
+        parse("2 / (a - 3)").eval(&bindings)
+    );
+    assert_eq!(
+        Err(EvalError::Domain { index: 3 }),
+        parse("x + sqrt(-a)").eval(&bindings)
+    );
+    assert_eq!(
+        Err(EvalError::DivisionByZero { index: 5 }),
+        parse("x + 1 / (pi - pi)").eval(&bindings)
+    );
+}
+
+#[test]
+fn tree_rules() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Child::*;
+    use parse_eq::tree::Rule;
+    use parse_eq::tree::Tree;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    let (a, b, c) = (
+        Meta(Variable::new("a")),
+        Meta(Variable::new("b")),
+        Meta(Variable::new("c")),
+    );
+    // ?a * (?b + ?c) => ?a * ?b + ?a * ?c
+    let distribute = Rule::new(
+        Tree::try_from_tokens(vec![a, Op(Multiply), LParen, b, Op(Add), c, RParen]).unwrap(),
+        Tree::try_from_tokens(vec![a, Op(Multiply), b, Op(Add), a, Op(Multiply), c]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!("?a", a.to_string());
+
+    let mut tree = parse("2 * (x * (y + 1) + z)");
+    assert_eq!(vec![vec![], vec![Right, Left]], tree.find(&distribute));
+
+    // Rewriting the inner match leaves the rest of the tree as it was.
+    let original = tree.clone();
+    assert!(tree.apply_at(&distribute, &[Right, Left]));
+    assert_eq!(parse("2 * (x * y + x * 1 + z)"), tree);
+    assert_eq!(parse("2 * (x * (y + 1) + z)"), original);
+    assert!(!tree.apply_at(&distribute, &[Right]));
+    assert!(!tree.apply_at(&distribute, &[Left, Left, Left]));
+
+    // A metavariable has to match the same sub-expression each time.
+    let cancel = Rule::new(
+        Tree::try_from_tokens(vec![a, Op(Subtract), a]).unwrap(),
+        parse("0"),
+    )
+    .unwrap();
+    assert_eq!(
+        vec![vec![Right]],
+        parse("x - 1 + ((y+1) - (y+1))").find(&cancel)
+    );
+
+    let mut tree = parse("(a + b) * (c + d)");
+    assert_eq!(1, tree.rewrite(std::slice::from_ref(&distribute), 100));
+    assert_eq!(
+        Lexer::new_inorder("(a + b) * c + (a + b) * d")
+            .unwrap()
+            .list,
+        tree.create_vec(Ordering::In)
+    );
+    let mut tree = parse("(x - x) * (y - y) + 1");
+    assert_eq!(2, tree.rewrite(&[distribute, cancel], 100));
+    assert_eq!(parse("0 * 0 + 1"), tree);
+
+    // Rules which never finish stop at the limit.
+    let commute = Rule::new(
+        Tree::try_from_tokens(vec![a, Op(Add), b]).unwrap(),
+        Tree::try_from_tokens(vec![b, Op(Add), a]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(5, parse("x + y").rewrite(&[commute], 5));
+
+    // The replacement can only use metavariables from the pattern.
+    assert_eq!(
+        None,
+        Rule::new(
+            Tree::try_from_tokens(vec![a, Op(Add), b]).unwrap(),
+            Tree::try_from_tokens(vec![c]).unwrap(),
+        )
+    );
+}
+
+#[test]
+fn tree_rule_parse() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Comparison;
+    use parse_eq::tree::Rule;
+    use parse_eq::tree::Tree;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    let base = Meta(Variable::new("base"));
+    assert_eq!(
+        vec![base, Op(Multiply), Meta(Variable::new("a"))],
+        Lexer::new_inorder("?base ?a").unwrap().list
+    );
+
+    let distribute = Rule::parse("?a * (?b + ?c) => ?a*?b + ?a*?c").unwrap();
+    let mut tree = parse("2 * (x + 1)");
+    assert_eq!(1, tree.rewrite(&[distribute], 10));
+    assert_eq!(parse("2x + 2*1"), tree);
+
+    // A rule only applies where its conditions hold.
+    let cancel = Rule::parse("?a / ?a => 1 if ?a != 0").unwrap();
+    assert_eq!(1, cancel.conditions().len());
+    assert_eq!(Comparison::NotEqual, cancel.conditions()[0].comparison);
+    let mut tree = parse("2/2 + (1 - 1)/(1 - 1) + x/x");
+    assert_eq!(1, tree.rewrite(std::slice::from_ref(&cancel), 10));
+    assert_eq!(parse("1 + (1 - 1)/(1 - 1) + x/x"), tree);
+
+    let rule = Rule::parse("?a^?n => ?a if ?n == 1 and ?a >= 0").unwrap();
+    assert_eq!(2, rule.conditions().len());
+    let mut tree = parse("2^1 + (-2)^1 + 2^(3 - 2)");
+    assert_eq!(2, tree.rewrite(&[rule], 10));
+    assert_eq!(parse("2 + (-2)^1 + 2"), tree);
+
+    // Several rules, one per line.
+    let rules = Rule::parse_list(
+        "# Identities
+        ?a + 0 => ?a
+        ?a * 1 => ?a
+
+        ?a - ?a => 0",
+    )
+    .unwrap();
+    assert_eq!(3, rules.len());
+    let mut tree = parse("(x * 1 + 0) - x");
+    assert_eq!(3, tree.rewrite(&rules, 10));
+    assert_eq!(parse("0"), tree);
+}
+
+#[test]
+fn tree_simplify() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::num::Num;
+    use parse_eq::token::Operator::*;
+    use parse_eq::token::Token::*;
+    use parse_eq::token::Variable;
+    use parse_eq::tree::Assumptions;
+    use parse_eq::tree::Tree;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    for (input, simplified) in [
+        ("-(-x)", "x"),
+        ("x - x", "0"),
+        ("(x + 0) * 1 - 0", "x"),
+        ("0 + y", "y"),
+        ("0 - y", "-y"),
+        ("x * x", "x^2"),
+        ("-x * x", "-x^2"),
+        ("-x * -x", "x^2"),
+        ("x * -1", "-x"),
+        ("x - -y", "x + y"),
+        ("(x + 1) * 0 + 2 * 3", "6"),
+        ("2^10 / 4", "256"),
+        ("0.5 + 0.25", "0.75"),
+        ("sqrt(2) + 0", "sqrt(2)"),
+        ("sin(pi) + cos(0) * x", "x"),
+        ("ln(e^(x + 1))", "x + 1"),
+        ("x^(3 - 2) + y^0", "x + y^0"),
+        ("1 / (1 - 1)", "1 / 0"),
+    ] {
+        assert_eq!(parse(simplified), parse(input).simplify(), "{}", input);
+    }
+    // Fractions stay exact.
+    let third = Num::from(1) / Num::from(3);
+    assert_eq!(
+        Tree::try_from_tokens(vec![Number(Num::from(1) / Num::from(2))]).unwrap(),
+        parse("1/3 + 1/6").simplify()
+    );
+    assert_eq!(
+        Tree::try_from_tokens(vec![Number(third), Op(Multiply), Var(Variable::Y)]).unwrap(),
+        parse("max(1/3, 0.25) * y").simplify()
+    );
+
+    // Dividing by an expression needs it not to be 0.
+    assert_eq!(parse("x / x"), parse("x / x").simplify());
+    assert_eq!(parse("1"), parse("(2 + pi) / (2 + pi)").simplify());
+    let assumptions = Assumptions {
+        nonzero: vec![Variable::X],
+        ..Default::default()
+    };
+    assert_eq!(parse("1"), parse("x / x").simplify_with(&assumptions));
+    assert_eq!(
+        parse("-1 + y / y").simplify(),
+        parse("-(2x) / (2x) + y / y").simplify_with(&assumptions)
+    );
+    let assumptions = Assumptions {
+        all_nonzero: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse("0"),
+        parse("-(2x) / (2x) + y / y").simplify_with(&assumptions)
+    );
+    assert_eq!(parse("1"), parse("(x y)^0").simplify_with(&assumptions));
+
+    // Nothing without a value is removed.
+    assert_eq!(parse("0 * sqrt(x)"), parse("0 * sqrt(x)").simplify());
+    assert_eq!(parse("1 ^ ln(x)"), parse("1^ln(x)").simplify());
+    assert_eq!(parse("1 / 0 * 0"), parse("(1/0) * 0").simplify());
+    assert_eq!(parse("1 / x - 1 / x"), parse("1/x - 1/x").simplify());
+    assert_eq!(parse("0"), parse("1/x - 1/x").simplify_with(&assumptions));
+    assert_eq!(parse("0"), parse("(sin(x) + x^2) * 0").simplify());
+}
+
+#[test]
+fn tree_expand() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::tree::Assumptions;
+    use parse_eq::tree::Tree;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    for (input, expanded) in [
+        ("a(b + c)", "a b + a c"),
+        ("(b - c) * a", "a b - a c"),
+        ("(a + b)^2", "a^2 + 2a b + b^2"),
+        ("(a + b)^3", "a^3 + 3a^2 b + 3a b^2 + b^3"),
+        ("(2x - 3)^2", "4x^2 - 12x + 9"),
+        ("(x - 1)(x + 1)", "x^2 - 1"),
+        ("(a + b)^2 - (a - b)^2", "4a b"),
+        ("x(x + 1) - x", "x^2"),
+        ("x / 2 + x / 2", "x"),
+        ("-(x - y)", "-x + y"),
+        ("(2x + 4) / 2", "x + 2"),
+        ("( ( 2 * ( 5 * 3 + 4 / ( 1 + 6 ) ) ) )", "218/7"),
+        (
+            "( ( 2 * ( ( 5 * x ) + 4 / ( ( 1 ) + ( y ) ) ) ) )",
+            "10x + 8 / (y + 1)",
+        ),
+        ("sin(2(x + 1)) + sin(2x + 2)", "2sin(2x + 2)"),
+        ("x^y * (x + 1)", "x x^y + x^y"),
+        // Large powers are worked out without multiplying one factor at a time.
+        ("(2x)^3 * x^100000000", "8x^100000003"),
+        ("(x - x)^1e30 + x", "x"),
+        ("(x + 1)^1000000", "(x + 1)^1000000"),
+    ] {
+        assert_eq!(
+            parse(expanded).simplify(),
+            parse(input).expand(),
+            "{}",
+            input
+        );
+    }
+    // Equal expressions expand to equal trees, whichever way they are written.
+    assert_eq!(
+        parse("(b + a)(a - b)").expand(),
+        parse("-b^2 + a^2").expand()
+    );
+    assert_eq!(
+        parse("(x + 1)^20").expand(),
+        parse("((x + 1)^4)^5").expand()
+    );
+
+    // Only factors known to be nonzero cancel, and nothing divided by 0 is taken to be 0.
+    let nonzero = Assumptions {
+        all_nonzero: true,
+        ..Default::default()
+    };
+    for (input, expanded, assuming) in [
+        ("(x^2 + x) / x", "x^2 / x + x / x", "x + 1"),
+        ("x^3 / x^5", "x^3 / x^5", "1 / x^2"),
+        ("x y / (x y)", "x y / (x y)", "1"),
+        (
+            "(2x)^3 / x^100000000",
+            "8x^3 / x^100000000",
+            "8 / x^99999997",
+        ),
+        ("(x - x) / x", "0 / x", "0"),
+        ("0 * (1 / 0)", "0 / 0", "0 / 0"),
+        ("0 / 0 + 1", "1 + 0 / 0", "1 + 0 / 0"),
+        ("y / x - y / x", "0 / x", "0"),
+        ("x * pi / pi", "x", "x"),
+    ] {
+        assert_eq!(
+            parse(expanded).simplify(),
+            parse(input).expand(),
+            "{}",
+            input
+        );
+        assert_eq!(
+            parse(assuming).simplify(),
+            parse(input).expand_with(&nonzero),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn tree_factor() {
+    use parse_eq::lexer::Lexer;
+    use parse_eq::lexer::Ordering;
+    use parse_eq::tree::Tree;
+
+    let parse = |input| Tree::new_pre_from_in(Lexer::new_inorder(input).unwrap().list).unwrap();
+    for (input, factored) in [
+        ("a b + a c", "a * (b + c)"),
+        ("6x + 9", "3 * (2 * x + 3)"),
+        ("x/2 + 1/4", "0.25 * (2 * x + 1)"),
+        ("x^2 - 5x + 6", "(x - 2) * (x - 3)"),
+        ("x^3 - 5x^2 + 6x", "x * (x - 2) * (x - 3)"),
+        ("2x^2 - 2", "2 * (x - 1) * (x + 1)"),
+        ("a x^2 - a", "a * (x - 1) * (x + 1)"),
+        ("x^2 - 2x + 1", "(x - 1) ^ 2"),
+        ("-x^2 + 5x - 6", "-(x - 2) * (x - 3)"),
+        ("2x^2 + x - 1", "(2 * x - 1) * (x + 1)"),
+        ("x^3 - x^2 + x - 1", "(x - 1) * (x ^ 2 + 1)"),
+        ("x^2 + 1", "x ^ 2 + 1"),
+        ("x y + x", "x * (y + 1)"),
+        ("(x + 1)(x + 2)", "(x + 1) * (x + 2)"),
+        ("6x", "6 * x"),
+        // Large powers are factored without being multiplied out.
+        ("x^100000000 - x", "x * (x ^ 99999999 - 1)"),
+        ("(x + 1)^1000000 - 1", "(x + 1) ^ 1000000 - 1"),
+        // Divisors are not taken out, as they only cancel when known to be nonzero.
+        ("x^3 / x - x", "x * (x ^ 2 / x - 1)"),
+        // Coefficients with too many divisors are not searched for roots.
+        (
+            "963761198400 x^2 + x + 963761198400",
+            "963761198400 * x ^ 2 + x + 963761198400",
+        ),
+        ("x^1000 + x + 963761198400", "x ^ 1000 + x + 963761198400"),
+    ] {
+        let check_in_order = Lexer::new_inorder(factored).unwrap().list;
+        assert_eq!(
+            check_in_order,
+            parse(input).factor().create_vec(Ordering::In),
+            "{}",
+            input
+        );
+    }
+    // Factoring is undone by expanding.
+    let cubic = parse("2x^3 - 3x^2 - 11x + 6");
+    assert_eq!(cubic.expand(), cubic.factor().expand());
+}